@@ -0,0 +1,158 @@
+use rust_decimal::{ Decimal, RoundingStrategy };
+
+use crate::api::error::ApiError;
+use super::response::{ SymbolFilter, SymbolInfo };
+
+/// Trading rules of a symbol taken from the Binance `exchangeInfo` filters
+#[derive(Debug, Clone, Default)]
+pub struct SymbolFilters {
+    pub tick_size: Decimal,
+    pub step_size: Decimal,
+    pub min_quantity: Decimal,
+    pub max_quantity: Decimal,
+    pub min_notional: Decimal,
+}
+
+impl SymbolFilters {
+    /// Rounds `quantity` down to the lot step size and validates it against the quantity
+    /// limits and the minimum notional at `price`
+    pub fn apply(&self, quantity: Decimal, price: Decimal) -> Result<Decimal, ApiError> {
+        let quantity = round_down_to_step(quantity, self.step_size);
+
+        if quantity < self.min_quantity || quantity.is_zero() {
+            return Err(
+                ApiError::ValidationError(
+                    format!(
+                        "Order quantity {quantity} is below the minimum quantity {}",
+                        self.min_quantity
+                    )
+                )
+            );
+        }
+
+        if !self.max_quantity.is_zero() && quantity > self.max_quantity {
+            return Err(
+                ApiError::ValidationError(
+                    format!(
+                        "Order quantity {quantity} is above the maximum quantity {}",
+                        self.max_quantity
+                    )
+                )
+            );
+        }
+
+        let notional = quantity * self.round_price(price);
+
+        if notional < self.min_notional {
+            return Err(
+                ApiError::ValidationError(
+                    format!(
+                        "Order notional {notional} is below the minimum notional {}",
+                        self.min_notional
+                    )
+                )
+            );
+        }
+
+        Ok(quantity)
+    }
+
+    pub fn round_price(&self, price: Decimal) -> Decimal {
+        round_down_to_step(price, self.tick_size)
+    }
+}
+
+impl From<&SymbolInfo> for SymbolFilters {
+    fn from(value: &SymbolInfo) -> Self {
+        value.filters
+            .iter()
+            .fold(SymbolFilters::default(), |mut filters, filter| {
+                match filter {
+                    SymbolFilter::PriceFilter { tick_size } => {
+                        filters.tick_size = *tick_size;
+                    }
+                    SymbolFilter::LotSize { min_qty, max_qty, step_size } => {
+                        filters.min_quantity = *min_qty;
+                        filters.max_quantity = *max_qty;
+                        filters.step_size = *step_size;
+                    }
+                    SymbolFilter::MinNotional { min_notional } => {
+                        filters.min_notional = *min_notional;
+                    }
+                    SymbolFilter::Other => {}
+                }
+
+                filters
+            })
+    }
+}
+
+fn round_down_to_step(value: Decimal, step: Decimal) -> Decimal {
+    if step.is_zero() {
+        return value;
+    }
+
+    ((value / step).round_dp_with_strategy(0, RoundingStrategy::ToZero) * step).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use rust_decimal::dec;
+
+    use crate::api::binance::response::ExchangeInfoResponse;
+    use super::*;
+
+    const EXCHANGE_INFO: &str =
+        r#"{
+    "timezone": "UTC",
+    "symbols": [{
+        "symbol": "BTCUSDT",
+        "status": "TRADING",
+        "filters": [
+            { "filterType": "PRICE_FILTER", "minPrice": "0.01000000", "maxPrice": "1000000.00000000", "tickSize": "0.01000000" },
+            { "filterType": "LOT_SIZE", "minQty": "0.00001000", "maxQty": "9000.00000000", "stepSize": "0.00001000" },
+            { "filterType": "ICEBERG_PARTS", "limit": 10 },
+            { "filterType": "NOTIONAL", "minNotional": "5.00000000", "applyMinToMarket": true, "maxNotional": "9000000.00000000", "applyMaxToMarket": false, "avgPriceMins": 5 }
+        ]
+    }]
+}"#;
+
+    fn btc_filters() -> SymbolFilters {
+        let exchange_info = ExchangeInfoResponse::deserialize_response(
+            Cow::from(EXCHANGE_INFO)
+        ).unwrap();
+
+        SymbolFilters::from(&exchange_info.symbols[0])
+    }
+
+    #[test]
+    fn parse_exchange_info_filters() {
+        let filters = btc_filters();
+
+        assert_eq!(filters.tick_size, dec!(0.01));
+        assert_eq!(filters.step_size, dec!(0.00001));
+        assert_eq!(filters.min_quantity, dec!(0.00001));
+        assert_eq!(filters.max_quantity, dec!(9000));
+        assert_eq!(filters.min_notional, dec!(5));
+    }
+
+    #[test]
+    fn rounds_quantity_down_to_step_size() {
+        let filters = btc_filters();
+
+        assert_eq!(filters.apply(dec!(0.0123456789), dec!(60000)).unwrap(), dec!(0.01234));
+        assert_eq!(filters.round_price(dec!(60000.129)), dec!(60000.12));
+    }
+
+    #[test]
+    fn rejects_orders_outside_filters() {
+        let filters = btc_filters();
+
+        assert!(filters.apply(dec!(0.000009), dec!(60000)).is_err());
+        assert!(filters.apply(dec!(0.00008), dec!(60000)).is_err());
+        assert!(filters.apply(dec!(9000.5), dec!(1)).is_err());
+        assert!(filters.apply(dec!(0.0001), dec!(60000)).is_ok());
+    }
+}
@@ -1,77 +1,271 @@
-use std::{ borrow::Cow, time::Duration };
+use std::{ borrow::Cow, collections::HashMap, sync::RwLock, time::Duration };
 
 use async_trait::async_trait;
 use binance_spot_connector_rust::{
-    http::{ request::Request, Credentials },
+    http::{ request::{ Request, RequestBuilder }, Credentials },
     hyper::BinanceHttpClient,
-    market::klines::Klines,
-    trade,
-    wallet::user_asset::UserAsset,
+    market::{ self, exchange_info::ExchangeInfo, klines::Klines },
+    trade::{ self, account::Account, new_order::NewOrder, order::{ NewOrderResponseType, Side } },
 };
 use hyper::client::HttpConnector;
 use hyper_tls::HttpsConnector;
+use tokio::sync::mpsc::Receiver;
 use rust_decimal::{ Decimal, prelude::FromPrimitive };
-use time::UtcDateTime;
 use tracing::{ debug, info, warn };
 
 use crate::{
     api::client::{ ApiClient, KLineParams },
-    core::market::{ Position, ProcessedCandle },
+    core::market::{ OrderExecution, ProcessedCandle },
+    api::supported_api::ExchangeEnvironment,
     strategy::timeframe::duration_into_kline_interval,
     ApiError,
 };
-use response::{ BinanceResponse, BalanceResponse };
+use filters::SymbolFilters;
+use response::{
+    AccountResponse,
+    BinanceResponse,
+    ExchangeInfoResponse,
+    OrderResponse,
+    ServerTimeResponse,
+    TickerPriceResponse,
+};
 
 // todo Should not be public
 pub mod response;
+mod filters;
+mod stream;
 
 const ENV_BINANCE_API_KEY: &str = "BINANCE_API_KEY";
 const ENV_BINANCE_API_SECRET: &str = "BINANCE_API_SECRET";
+const ENV_BINANCE_TESTNET_API_KEY: &str = "BINANCE_TESTNET_API_KEY";
+const ENV_BINANCE_TESTNET_API_SECRET: &str = "BINANCE_TESTNET_API_SECRET";
+const BINANCE_API_URL: &str = "https://api.binance.com";
+const BINANCE_STREAM_URL: &str = "wss://stream.binance.com:9443/stream";
+const BINANCE_TESTNET_API_URL: &str = "https://testnet.binance.vision";
+const BINANCE_TESTNET_STREAM_URL: &str = "wss://stream.testnet.binance.vision/stream";
+const MAX_RETRIES: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// Longest `Retry-After` waited for, requests asked to wait longer fail right away
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Exponential backoff of requests failing with a retryable error
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every following one
+    pub initial_backoff: Duration,
+}
 
 pub struct BinanceApi {
     client: BinanceHttpClient<HttpsConnector<HttpConnector>>,
+    stream_url: String,
+    retry_policy: RetryPolicy,
+    symbol_filters: RwLock<HashMap<String, SymbolFilters>>,
 }
 
 impl BinanceApi {
-    const QUANTITY_FLOAT_PRECISION: u32 = 5;
+    pub fn new(environment: ExchangeEnvironment) -> Self {
+        let (api_key, api_secret) = match environment {
+            ExchangeEnvironment::Mainnet => (ENV_BINANCE_API_KEY, ENV_BINANCE_API_SECRET),
+            ExchangeEnvironment::Testnet =>
+                (ENV_BINANCE_TESTNET_API_KEY, ENV_BINANCE_TESTNET_API_SECRET),
+        };
 
-    pub fn new() -> Self {
         let credentials = Credentials::from_hmac(
-            dotenv::var(ENV_BINANCE_API_KEY).expect("BINANCE_API_KEY is missing"),
-            dotenv::var(ENV_BINANCE_API_SECRET).expect("BINANCE_API_SECRET is missing")
+            dotenv::var(api_key).unwrap_or_else(|_| panic!("{api_key} is missing")),
+            dotenv::var(api_secret).unwrap_or_else(|_| panic!("{api_secret} is missing"))
         );
 
+        Self::market_data(environment).with_credentials(credentials)
+    }
+
+    /// Client of the public market data endpoints, reading no API credentials. Account and
+    /// order requests fail on it
+    pub fn market_data(environment: ExchangeEnvironment) -> Self {
+        let (base_url, stream_url) = match environment {
+            ExchangeEnvironment::Mainnet => (BINANCE_API_URL, BINANCE_STREAM_URL),
+            ExchangeEnvironment::Testnet => (BINANCE_TESTNET_API_URL, BINANCE_TESTNET_STREAM_URL),
+        };
+
+        Self::unsigned(base_url, stream_url)
+    }
+
+    /// Client sending its REST requests to `base_url` instead of the production endpoint
+    pub fn with_base_url(base_url: &str, credentials: Credentials) -> Self {
+        Self::unsigned(base_url, BINANCE_STREAM_URL).with_credentials(credentials)
+    }
+
+    fn unsigned(base_url: &str, stream_url: &str) -> Self {
+        Self {
+            client: BinanceHttpClient::with_url(base_url),
+            stream_url: stream_url.to_string(),
+            retry_policy: RetryPolicy::default(),
+            symbol_filters: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn with_credentials(self, credentials: Credentials) -> Self {
         Self {
-            client: BinanceHttpClient::default().credentials(credentials),
+            client: self.client.credentials(credentials),
+            ..self
         }
     }
 
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
     pub async fn get_kline_data(&self, params: Klines) -> Result<Vec<BinanceResponse>, ApiError> {
         let request = Request::from(params);
 
         debug!("Requesting Kline data from binance with params: {:?}", request.params());
 
-        let response = self.client.send(request).await?.into_body_str().await?;
+        let response = self.send(request).await?;
 
         let raw_kline_data = BinanceResponse::deserialize_response(Cow::from(response))?;
 
         Ok(raw_kline_data)
     }
 
-    fn get_decimal_quantity(quantity: f64) -> Result<Decimal, ApiError> {
-        let mut decimal = Decimal::from_f64(quantity).ok_or(
-            ApiError::ParseError("Failed to parse quantity when creating an order".to_string())
-        )?;
+    pub async fn get_symbol_filters(&self, symbol: &str) -> Result<SymbolFilters, ApiError> {
+        let cached_filters = self.symbol_filters
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(symbol)
+            .cloned();
+
+        if let Some(filters) = cached_filters {
+            return Ok(filters);
+        }
+
+        let response = self.send(Request::from(ExchangeInfo::new().symbol(symbol))).await?;
+
+        let exchange_info = ExchangeInfoResponse::deserialize_response(Cow::from(response))?;
+        let symbol_info = exchange_info.symbols
+            .iter()
+            .find(|info| info.symbol == symbol)
+            .ok_or(ApiError::NotFound(format!("No exchange info received for {symbol}")))?;
+
+        let filters = SymbolFilters::from(symbol_info);
+
+        info!("Loaded trading rules for {}: {:?}", symbol, filters);
+
+        self.symbol_filters
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(symbol.to_string(), filters.clone());
+
+        Ok(filters)
+    }
+
+    /// Sends `request`, retrying it with an exponential backoff while it fails with a
+    /// retryable error. A `Retry-After` of the response replaces the backoff
+    async fn send(&self, request: Request) -> Result<String, ApiError> {
+        self.send_with_retries(request, ApiError::is_retryable).await
+    }
+
+    /// Places `order`, retried only when Binance rejected it unprocessed so an order with an
+    /// unknown execution status is never placed twice
+    async fn send_order(&self, order: NewOrder) -> Result<OrderExecution, ApiError> {
+        let response = self.send_with_retries(
+            Request::from(order),
+            ApiError::is_unprocessed
+        ).await?;
+
+        let order_response = OrderResponse::deserialize_response(Cow::from(response))?;
+
+        if order_response.executed_qty == 0_f64 {
+            return Err(
+                ApiError::OrderError(
+                    format!(
+                        "Order {} for {} was not filled, status: {}",
+                        order_response.order_id,
+                        order_response.symbol,
+                        order_response.status
+                    )
+                )
+            );
+        }
+
+        let execution = OrderExecution::from(order_response);
+
+        info!("Order executed: {:?}", execution);
+
+        Ok(execution)
+    }
+
+    async fn get_order_quantity(
+        &self,
+        symbol: &str,
+        quantity: f64,
+        price: f64
+    ) -> Result<Decimal, ApiError> {
+        let decimal_quantity = BinanceApi::to_decimal(quantity, "quantity")?;
+        let decimal_price = BinanceApi::to_decimal(price, "price")?;
+
+        self.get_symbol_filters(symbol).await?.apply(decimal_quantity, decimal_price)
+    }
+
+    async fn send_with_retries(
+        &self,
+        request: Request,
+        is_retryable: fn(&ApiError) -> bool
+    ) -> Result<String, ApiError> {
+        let mut backoff = self.retry_policy.initial_backoff;
+
+        for retry in 1..=self.retry_policy.max_retries {
+            match self.send_once(copy_request(&request)).await {
+                Err(e) if
+                    is_retryable(&e) &&
+                    e.retry_after().is_none_or(|retry_after| retry_after <= MAX_RETRY_AFTER)
+                => {
+                    let delay = e.retry_after().unwrap_or(backoff);
+
+                    warn!(
+                        "Binance request to {} failed, retry {}/{} in {:?}: {}",
+                        request.path(),
+                        retry,
+                        self.retry_policy.max_retries,
+                        delay,
+                        e
+                    );
+
+                    tokio::time::sleep(delay).await;
+                    backoff *= 2;
+                }
+                result => {
+                    return result;
+                }
+            }
+        }
 
-        decimal.rescale(BinanceApi::QUANTITY_FLOAT_PRECISION);
+        self.send_once(request).await
+    }
+
+    async fn send_once(&self, request: Request) -> Result<String, ApiError> {
+        Ok(self.client.send(request).await?.into_body_str().await?)
+    }
 
-        Ok(decimal)
+    fn to_decimal(value: f64, name: &str) -> Result<Decimal, ApiError> {
+        Decimal::from_f64(value).ok_or(
+            ApiError::ParseError(format!("Failed to parse {name} when creating an order"))
+        )
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: MAX_RETRIES,
+            initial_backoff: INITIAL_BACKOFF,
+        }
     }
 }
 
 impl Default for BinanceApi {
     fn default() -> Self {
-        Self::new()
+        Self::new(ExchangeEnvironment::default())
     }
 }
 
@@ -98,15 +292,35 @@ impl ApiClient for BinanceApi {
             ApiError::ParseError("Invalid interval provided".to_string())
         )?;
 
-        let params = Klines::new(symbol, kline_interval).limit(1);
+        // The last kline is still forming, the one before it is the latest closed candle
+        let params = Klines::new(symbol, kline_interval).limit(2);
 
         let response = self.get_kline_data(params).await?;
+        let server_time = self.get_server_time().await?;
 
-        if response.is_empty() {
-            return Err(ApiError::MarketError("No data received from binance".to_string()));
-        }
+        let closed_candle = response
+            .iter()
+            .rev()
+            .find(|kline| {
+                let close_time = kline.close_timestamp.unix_timestamp() * 1000;
+
+                close_time < i64::try_from(server_time).unwrap_or(i64::MAX)
+            })
+            .ok_or(ApiError::MarketError(format!("No closed {symbol} candle received")))?;
+
+        Ok(ProcessedCandle::from(closed_candle))
+    }
+
+    async fn get_latest_price(&self, symbol: &'_ str) -> Result<f64, ApiError> {
+        let response = self.send(Request::from(market::ticker_price().symbol(symbol))).await?;
 
-        Ok(ProcessedCandle::from(&response[0]))
+        Ok(TickerPriceResponse::deserialize_response(Cow::from(response))?.price)
+    }
+
+    async fn get_server_time(&self) -> Result<u64, ApiError> {
+        let response = self.send(Request::from(market::time())).await?;
+
+        Ok(ServerTimeResponse::deserialize_response(Cow::from(response))?.server_time)
     }
 
     async fn place_order_to_buy(
@@ -114,47 +328,94 @@ impl ApiClient for BinanceApi {
         symbol: &str,
         quantity: f64,
         price: f64
-    ) -> Result<Position, ApiError> {
-        let decimal_quantity = BinanceApi::get_decimal_quantity(quantity)?;
+    ) -> Result<OrderExecution, ApiError> {
+        let decimal_quantity = self.get_order_quantity(symbol, quantity, price).await?;
 
         let order = trade
-            ::new_order(symbol, trade::order::Side::Buy, "MARKET")
-            .quantity(decimal_quantity);
-
-        info!("Created order to buy for {} {}", symbol, quantity);
+            ::new_order(symbol, Side::Buy, "MARKET")
+            .quantity(decimal_quantity)
+            .new_order_resp_type(NewOrderResponseType::Full);
 
-        self.client.send(order).await?;
+        info!("Created order to buy for {} {}", symbol, decimal_quantity);
 
-        Ok(Position::new(symbol.to_string(), price, quantity, UtcDateTime::now()))
+        self.send_order(order).await
     }
 
-    async fn place_order_to_sell(&self, symbol: &str, quantity: f64) -> Result<(), ApiError> {
-        let decimal_quantity = BinanceApi::get_decimal_quantity(quantity)?;
+    async fn place_order_to_sell(
+        &self,
+        symbol: &str,
+        quantity: f64,
+        price: f64
+    ) -> Result<OrderExecution, ApiError> {
+        let decimal_quantity = self.get_order_quantity(symbol, quantity, price).await?;
 
         let order = trade
-            ::new_order(symbol, trade::order::Side::Sell, "MARKET")
-            .quantity(decimal_quantity);
+            ::new_order(symbol, Side::Sell, "MARKET")
+            .quantity(decimal_quantity)
+            .new_order_resp_type(NewOrderResponseType::Full);
 
-        info!("Created order to Sell for {} {}", symbol, quantity);
-        self.client.send(order).await?;
+        info!("Created order to Sell for {} {}", symbol, decimal_quantity);
 
-        Ok(())
+        self.send_order(order).await
     }
 
+    /// Free balance of the `symbol` asset from the spot account endpoint, which unlike the
+    /// wallet endpoints is also served by the testnet
     async fn get_account_balance(&self, symbol: &'_ str) -> Result<f64, ApiError> {
-        let user_asset_request = UserAsset::new().asset(symbol);
-        let user_asset_response = self.client
-            .send(user_asset_request).await?
-            .into_body_str().await?;
+        let account_response = self.send(Request::from(Account::new())).await?;
 
-        let assets = BalanceResponse::deserialize_response(Cow::from(user_asset_response))?;
+        let account = AccountResponse::deserialize_response(Cow::from(account_response))?;
 
-        warn!("account data response: {:?}", assets);
+        debug!("Account balances response: {:?}", account.balances);
 
-        let account_balance = assets
-            .first()
-            .ok_or(ApiError::ValidationError("Empty balance data received".to_string()))?;
+        let account_balance = account.balances
+            .iter()
+            .find(|balance| balance.asset == symbol)
+            .ok_or(ApiError::NotFound(format!("No {symbol} balance received")))?;
 
         Ok(account_balance.free)
     }
+
+    async fn load_trading_rules(&self, symbol: &'_ str) -> Result<(), ApiError> {
+        self.get_symbol_filters(symbol).await.map(|_| ())
+    }
+
+    async fn subscribe_candles(
+        &self,
+        symbol: &'_ str,
+        interval: &'_ Duration
+    ) -> Result<Option<Receiver<ProcessedCandle>>, ApiError> {
+        let kline_interval = duration_into_kline_interval(interval).ok_or(
+            ApiError::ParseError("Invalid interval provided".to_string())
+        )?;
+
+        Ok(
+            Some(
+                stream::spawn_kline_stream(
+                    self.stream_url.clone(),
+                    symbol.to_string(),
+                    kline_interval
+                )
+            )
+        )
+    }
+}
+
+/// `Request` is not `Clone`, every attempt of a retried request is rebuilt from its parts
+fn copy_request(request: &Request) -> Request {
+    let params = request.params()
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()));
+
+    let mut builder = RequestBuilder::new(request.method().clone(), request.path()).params(params);
+
+    if let Some(credentials) = request.credentials() {
+        builder = builder.credentials(credentials.clone());
+    }
+
+    if *request.sign() {
+        builder = builder.sign();
+    }
+
+    Request::from(builder)
 }
@@ -1,5 +1,6 @@
 use std::{ borrow::Cow, fmt };
 
+use rust_decimal::Decimal;
 use serde::{ de::Visitor, Deserialize, Deserializer };
 use time::UtcDateTime;
 
@@ -20,22 +21,17 @@ pub struct BinanceResponse {
     pub taker_buy_quote_asset_vol: f64,
 }
 
+/// Spot account information, only the balances are read
 #[derive(Debug, Deserialize)]
-#[allow(dead_code)]
-pub struct BalanceResponse {
-    asset: String,
+pub struct AccountResponse {
+    pub balances: Vec<AccountBalance>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AccountBalance {
+    pub asset: String,
     #[serde(deserialize_with = "deserialize_float")]
     pub free: f64,
-    #[serde(deserialize_with = "deserialize_float")]
-    locked: f64,
-    #[serde(deserialize_with = "deserialize_float")]
-    freeze: f64,
-    #[serde(deserialize_with = "deserialize_float")]
-    withdrawing: f64,
-    #[serde(deserialize_with = "deserialize_float")]
-    ipoable: f64,
-    #[serde(rename = "btcValuation", deserialize_with = "deserialize_float")]
-    btc_valuation: f64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -92,11 +88,11 @@ impl From<RawResponse> for BinanceResponse {
     }
 }
 
-impl BalanceResponse {
-    pub fn deserialize_response(json_data: Cow<'_, str>) -> Result<Vec<Self>, ApiError> {
-        let balance_response: Vec<BalanceResponse> = serde_json::from_str(&json_data)?;
+impl AccountResponse {
+    pub fn deserialize_response(json_data: Cow<'_, str>) -> Result<Self, ApiError> {
+        let account: AccountResponse = serde_json::from_str(&json_data)?;
 
-        Ok(balance_response)
+        Ok(account)
     }
 }
 
@@ -137,3 +133,174 @@ fn deserialize_float<'de, D>(deserializer: D) -> Result<f64, D::Error> where D:
 
     deserializer.deserialize_str(FloatVisitor)
 }
+
+#[derive(Debug, Deserialize)]
+pub struct ExchangeInfoResponse {
+    pub symbols: Vec<SymbolInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SymbolInfo {
+    pub symbol: String,
+    pub filters: Vec<SymbolFilter>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "filterType")]
+pub enum SymbolFilter {
+    #[serde(rename = "PRICE_FILTER", rename_all = "camelCase")] PriceFilter {
+        tick_size: Decimal,
+    },
+    #[serde(rename = "LOT_SIZE", rename_all = "camelCase")] LotSize {
+        min_qty: Decimal,
+        max_qty: Decimal,
+        step_size: Decimal,
+    },
+    #[serde(rename = "MIN_NOTIONAL", alias = "NOTIONAL", rename_all = "camelCase")] MinNotional {
+        min_notional: Decimal,
+    },
+    #[serde(other)]
+    Other,
+}
+
+impl ExchangeInfoResponse {
+    pub fn deserialize_response(json_data: Cow<'_, str>) -> Result<Self, ApiError> {
+        let exchange_info: ExchangeInfoResponse = serde_json::from_str(&json_data)?;
+
+        Ok(exchange_info)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderResponse {
+    pub symbol: String,
+    pub order_id: u64,
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub transact_time: UtcDateTime,
+    #[serde(deserialize_with = "deserialize_float")]
+    pub executed_qty: f64,
+    #[serde(deserialize_with = "deserialize_float")]
+    pub cummulative_quote_qty: f64,
+    pub status: String,
+    #[serde(default)]
+    pub fills: Vec<OrderFillResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderFillResponse {
+    #[serde(deserialize_with = "deserialize_float")]
+    pub price: f64,
+    #[serde(deserialize_with = "deserialize_float")]
+    pub qty: f64,
+    #[serde(deserialize_with = "deserialize_float")]
+    pub commission: f64,
+    pub commission_asset: String,
+    pub trade_id: u64,
+}
+
+impl OrderResponse {
+    pub fn deserialize_response(json_data: Cow<'_, str>) -> Result<Self, ApiError> {
+        let order_response: OrderResponse = serde_json::from_str(&json_data)?;
+
+        Ok(order_response)
+    }
+}
+
+/// Price of the last trade of a symbol
+#[derive(Debug, Deserialize)]
+pub struct TickerPriceResponse {
+    #[serde(deserialize_with = "deserialize_float")]
+    pub price: f64,
+}
+
+impl TickerPriceResponse {
+    pub fn deserialize_response(json_data: Cow<'_, str>) -> Result<Self, ApiError> {
+        let ticker_price: TickerPriceResponse = serde_json::from_str(&json_data)?;
+
+        Ok(ticker_price)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerTimeResponse {
+    pub server_time: u64,
+}
+
+impl ServerTimeResponse {
+    pub fn deserialize_response(json_data: Cow<'_, str>) -> Result<Self, ApiError> {
+        let server_time: ServerTimeResponse = serde_json::from_str(&json_data)?;
+
+        Ok(server_time)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KlineStreamEvent {
+    pub data: KlineEvent,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KlineEvent {
+    #[serde(rename = "k")]
+    pub kline: StreamKline,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+pub struct StreamKline {
+    #[serde(rename = "t", deserialize_with = "deserialize_timestamp")]
+    open_timestamp: UtcDateTime,
+    #[serde(rename = "T", deserialize_with = "deserialize_timestamp")]
+    close_timestamp: UtcDateTime,
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "o", deserialize_with = "deserialize_float")]
+    open_price: f64,
+    #[serde(rename = "c", deserialize_with = "deserialize_float")]
+    close_price: f64,
+    #[serde(rename = "h", deserialize_with = "deserialize_float")]
+    high_price: f64,
+    #[serde(rename = "l", deserialize_with = "deserialize_float")]
+    low_price: f64,
+    #[serde(rename = "v", deserialize_with = "deserialize_float")]
+    volume: f64,
+    #[serde(rename = "n")]
+    num_of_trades: u32,
+    #[serde(rename = "x")]
+    pub is_closed: bool,
+    #[serde(rename = "q", deserialize_with = "deserialize_float")]
+    quote_asset_vol: f64,
+    #[serde(rename = "V", deserialize_with = "deserialize_float")]
+    taker_buy_base_asset_vol: f64,
+    #[serde(rename = "Q", deserialize_with = "deserialize_float")]
+    taker_buy_quote_asset_vol: f64,
+}
+
+impl KlineStreamEvent {
+    pub fn deserialize_event(json_data: &str) -> Result<Self, ApiError> {
+        let event: KlineStreamEvent = serde_json::from_str(json_data)?;
+
+        Ok(event)
+    }
+}
+
+impl From<StreamKline> for BinanceResponse {
+    fn from(value: StreamKline) -> Self {
+        Self {
+            open_timestamp: value.open_timestamp,
+            open_price: value.open_price,
+            high_price: value.high_price,
+            low_price: value.low_price,
+            close_price: value.close_price,
+            volume: value.volume,
+            close_timestamp: value.close_timestamp,
+            quote_asset_vol: value.quote_asset_vol,
+            num_of_trades: value.num_of_trades,
+            taker_buy_base_asset_vol: value.taker_buy_base_asset_vol,
+            taker_buy_quote_asset_vol: value.taker_buy_quote_asset_vol,
+        }
+    }
+}
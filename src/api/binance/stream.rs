@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use binance_spot_connector_rust::{
+    market::klines::KlineInterval,
+    market_stream::kline::KlineStream,
+    tokio_tungstenite::BinanceWebSocketClient,
+};
+use futures_util::StreamExt;
+use tokio::{ sync::mpsc::{ self, Receiver, Sender }, time::sleep };
+use tracing::{ debug, error, info, warn };
+
+use crate::core::market::ProcessedCandle;
+use super::response::{ BinanceResponse, KlineStreamEvent };
+
+const CANDLE_CHANNEL_CAPACITY: usize = 64;
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+enum StreamEnd {
+    Disconnected,
+    ReceiverDropped,
+}
+
+/// Spawns a task streaming closed klines of `symbol` from the Binance websocket,
+/// reconnecting with exponential backoff whenever the connection drops
+pub fn spawn_kline_stream(
+    stream_url: String,
+    symbol: String,
+    interval: KlineInterval
+) -> Receiver<ProcessedCandle> {
+    let (sender, receiver) = mpsc::channel(CANDLE_CHANNEL_CAPACITY);
+
+    tokio::spawn(async move {
+        let mut reconnect_delay = INITIAL_RECONNECT_DELAY;
+
+        loop {
+            match stream_klines(&stream_url, &symbol, interval, &sender).await {
+                Ok(StreamEnd::ReceiverDropped) => {
+                    info!("Kline stream for {} is no longer consumed, closing", symbol);
+                    return;
+                }
+                Ok(StreamEnd::Disconnected) => {
+                    warn!("Kline stream for {} disconnected", symbol);
+                    reconnect_delay = INITIAL_RECONNECT_DELAY;
+                }
+                Err(e) => error!("Kline stream for {} failed: {}", symbol, e),
+            }
+
+            info!("Reconnecting kline stream for {} in {:?}", symbol, reconnect_delay);
+
+            sleep(reconnect_delay).await;
+            reconnect_delay = (reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    });
+
+    receiver
+}
+
+async fn stream_klines(
+    stream_url: &str,
+    symbol: &str,
+    interval: KlineInterval,
+    sender: &Sender<ProcessedCandle>
+) -> Result<StreamEnd, String> {
+    let (mut connection, _) = BinanceWebSocketClient::connect_async(stream_url).await.map_err(|e|
+        format!("{e}")
+    )?;
+
+    connection.subscribe(vec![&KlineStream::new(symbol, interval).into()]).await;
+
+    info!("Subscribed to {} kline stream with interval {}", symbol, interval);
+
+    let socket = connection.as_mut();
+
+    while let Some(message) = socket.next().await {
+        let message = message.map_err(|e| format!("{e}"))?;
+
+        if message.is_close() {
+            return Ok(StreamEnd::Disconnected);
+        }
+
+        let Ok(text) = message.to_text() else {
+            continue;
+        };
+
+        let Ok(event) = KlineStreamEvent::deserialize_event(text) else {
+            debug!("Skipping non kline stream message: {}", text);
+            continue;
+        };
+
+        if !event.data.kline.is_closed {
+            continue;
+        }
+
+        let candle = ProcessedCandle::from(BinanceResponse::from(event.data.kline));
+
+        debug!("Received closed kline from stream: {:?}", candle);
+
+        if sender.send(candle).await.is_err() {
+            return Ok(StreamEnd::ReceiverDropped);
+        }
+    }
+
+    Ok(StreamEnd::Disconnected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_closed_kline_event() {
+        let message =
+            r#"{"stream":"btcusdt@kline_30m","data":{"e":"kline","E":1700001800001,"s":"BTCUSDT","k":{"t":1700000000000,"T":1700001799999,"s":"BTCUSDT","i":"30m","f":100,"L":200,"o":"100.50","c":"101.20","h":"102.00","l":"99.80","v":"12.5","n":101,"x":true,"q":"1260.0","V":"6.5","Q":"655.0","B":"0"}}}"#;
+
+        let event = KlineStreamEvent::deserialize_event(message).unwrap();
+
+        assert!(event.data.kline.is_closed);
+
+        let candle = ProcessedCandle::from(BinanceResponse::from(event.data.kline));
+
+        assert_eq!(candle.timestamp, 1_700_000_000);
+        assert_eq!(candle.close, 101.2);
+        assert_eq!(candle.volume, 12.5);
+    }
+
+    #[test]
+    fn skip_subscription_response() {
+        assert!(KlineStreamEvent::deserialize_event(r#"{"result":null,"id":0}"#).is_err());
+    }
+}
@@ -1,10 +1,11 @@
-use std::time::Duration;
+use std::time::{ Duration, SystemTime, UNIX_EPOCH };
 
 use async_trait::async_trait;
 use binance_spot_connector_rust::market::klines::Klines;
+use tokio::sync::mpsc::Receiver;
 
 use crate::{
-    core::market::{ Position, ProcessedCandle },
+    core::market::{ OrderExecution, ProcessedCandle },
     strategy::{
         strategy::Strategy,
         timeframe::{ duration_from_kline_interval, duration_into_kline_interval },
@@ -22,25 +23,64 @@ pub struct KLineParams {
 }
 
 #[async_trait]
-pub trait ApiClient {
+pub trait ApiClient: Send + Sync {
     async fn get_candles(&self, params: KLineParams) -> Result<Vec<ProcessedCandle>, ApiError>;
 
+    /// Returns the latest closed candle of `symbol`, never the one still forming
     async fn get_latest_candle(
         &self,
         symbol: &'_ str,
         interval: &'_ Duration
     ) -> Result<ProcessedCandle, ApiError>;
 
+    /// Price of the last trade of `symbol`
+    async fn get_latest_price(&self, symbol: &'_ str) -> Result<f64, ApiError>;
+
     async fn place_order_to_buy(
         &self,
         symbol: &'_ str,
         quantity: f64,
         price: f64,
-    ) -> Result<Position, ApiError>;
+    ) -> Result<OrderExecution, ApiError>;
 
-    async fn place_order_to_sell(&self, symbol: &'_ str, quantity: f64) -> Result<(), ApiError>;
+    async fn place_order_to_sell(
+        &self,
+        symbol: &'_ str,
+        quantity: f64,
+        price: f64
+    ) -> Result<OrderExecution, ApiError>;
 
     async fn get_account_balance(&self, symbol: &'_ str) -> Result<f64, ApiError>;
+
+    /// Current exchange time in milliseconds, candles open and close on this clock
+    async fn get_server_time(&self) -> Result<u64, ApiError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| ApiError::ParseError(format!("System time is before epoch: {e}")))?;
+
+        Ok(u64::try_from(now.as_millis()).unwrap_or(u64::MAX))
+    }
+
+    /// Loads the exchange trading rules for `symbol` before any order is placed
+    async fn load_trading_rules(&self, _symbol: &'_ str) -> Result<(), ApiError> {
+        Ok(())
+    }
+
+    /// Restores the state a simulating client left behind on its previous run, before the
+    /// balance is requested
+    async fn restore_state(&self) -> Result<(), ApiError> {
+        Ok(())
+    }
+
+    /// Starts pushing closed candles of `symbol` as they complete, `None` when the client
+    /// has no streaming support and candles have to be polled
+    async fn subscribe_candles(
+        &self,
+        _symbol: &'_ str,
+        _interval: &'_ Duration
+    ) -> Result<Option<Receiver<ProcessedCandle>>, ApiError> {
+        Ok(None)
+    }
 }
 
 impl KLineParams {
@@ -64,6 +104,15 @@ impl KLineParams {
         }
     }
 
+    pub fn candles_count(&self) -> usize {
+        self.candles_count
+    }
+
+    /// Requested open time range in milliseconds, `None` for an unbounded side
+    pub fn time_range(&self) -> (Option<u64>, Option<u64>) {
+        (self.start_time, self.end_time)
+    }
+
     pub fn end_time(mut self, end_time: u64) -> Self {
         self.end_time = Some(end_time);
         self
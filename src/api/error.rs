@@ -1,4 +1,26 @@
-use std::{ error::Error, fmt::Display };
+use std::{ collections::HashMap, error::Error, fmt::Display, time::Duration };
+
+use binance_spot_connector_rust::{ http::error::ClientError, hyper::Error as BinanceClientError };
+use serde::Deserialize;
+
+/// Binance codes of requests rejected before they were processed
+const UNPROCESSED_ERROR_CODES: [i32; 5] = [
+    -1001, // Internal error, unable to process the request
+    -1003, // Too many requests
+    -1008, // Server is overloaded
+    -1015, // Too many new orders
+    -1021, // Timestamp outside of the receive window
+];
+/// Binance codes of requests whose execution status is unknown
+const UNKNOWN_STATUS_ERROR_CODES: [i32; 3] = [
+    -1000, // Unknown error while processing the request
+    -1006, // Unexpected response from the backend
+    -1007, // Timeout waiting for the backend
+];
+const STATUS_IP_BANNED: u16 = 418;
+const STATUS_TOO_MANY_REQUESTS: u16 = 429;
+const STATUS_SERVER_ERROR: u16 = 500;
+const RETRY_AFTER_HEADER: &str = "retry-after";
 
 #[derive(Debug)]
 pub enum ApiError {
@@ -8,6 +30,66 @@ pub enum ApiError {
     OrderError(String),
     ValidationError(String),
     NotFound(String),
+    StorageError(String),
+    /// Error body returned by Binance with the HTTP status of the response
+    BinanceError {
+        status: u16,
+        code: i32,
+        msg: String,
+        /// Delay requested by the `Retry-After` header of the response
+        retry_after: Option<Duration>,
+    },
+    /// Error response without the Binance error schema, e.g. from a proxy in front of the API
+    HttpError {
+        status: u16,
+        body: String,
+        retry_after: Option<Duration>,
+    },
+}
+
+/// Error schema of the Binance REST API
+#[derive(Debug, Deserialize)]
+struct BinanceErrorBody {
+    code: i32,
+    msg: String,
+}
+
+impl ApiError {
+    /// Whether Binance refused the request without processing it, so sending it again can not
+    /// execute it twice. A `418` ban after ignored rate limits is never retried
+    pub fn is_unprocessed(&self) -> bool {
+        match self {
+            ApiError::BinanceError { status, code, .. } =>
+                *status == STATUS_TOO_MANY_REQUESTS ||
+                    (*status != STATUS_IP_BANNED && UNPROCESSED_ERROR_CODES.contains(code)),
+            ApiError::HttpError { status, .. } => *status == STATUS_TOO_MANY_REQUESTS,
+            _ => false,
+        }
+    }
+
+    /// Whether sending the request again may succeed, which includes failures where the
+    /// request might have been executed already
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ApiError::NetworkError(_) => true,
+            ApiError::BinanceError { status, code, .. } =>
+                self.is_unprocessed() ||
+                    *status >= STATUS_SERVER_ERROR ||
+                    UNKNOWN_STATUS_ERROR_CODES.contains(code),
+            ApiError::HttpError { status, .. } =>
+                self.is_unprocessed() || *status >= STATUS_SERVER_ERROR,
+            _ => false,
+        }
+    }
+
+    /// Delay the exchange asked for before the request is sent again
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ApiError::BinanceError { retry_after, .. } | ApiError::HttpError { retry_after, .. } =>
+                *retry_after,
+            _ => None,
+        }
+    }
 }
 
 impl Error for ApiError {}
@@ -21,6 +103,10 @@ impl Display for ApiError {
             ApiError::OrderError(reason) => write!(f, "Order error: {reason}"),
             ApiError::ValidationError(reason) => write!(f, "Invalid input: {reason}"),
             ApiError::NotFound(reason) => write!(f, "Not found: {reason}"),
+            ApiError::StorageError(reason) => write!(f, "Storage error: {reason}"),
+            ApiError::BinanceError { status, code, msg, .. } =>
+                write!(f, "Binance error {code} (HTTP {status}): {msg}"),
+            ApiError::HttpError { status, body, .. } => write!(f, "HTTP {status}: {body}"),
         }
     }
 }
@@ -31,8 +117,95 @@ impl From<serde_json::error::Error> for ApiError {
     }
 }
 
-impl From<binance_spot_connector_rust::hyper::Error> for ApiError {
-    fn from(value: binance_spot_connector_rust::hyper::Error) -> Self {
-        Self::NetworkError(format!("Failed to fetch: {value:?}"))
+impl From<std::io::Error> for ApiError {
+    fn from(value: std::io::Error) -> Self {
+        Self::StorageError(format!("{value}"))
+    }
+}
+
+impl From<BinanceClientError> for ApiError {
+    fn from(value: BinanceClientError) -> Self {
+        let (status, body, headers) = match value {
+            BinanceClientError::Client(ClientError::Structured(error)) => {
+                return Self::BinanceError {
+                    status: error.status_code,
+                    code: i32::from(error.data.code),
+                    msg: error.data.message,
+                    retry_after: retry_after(&error.headers),
+                };
+            }
+            BinanceClientError::Client(ClientError::Raw(error)) =>
+                (error.status_code, error.data, error.headers),
+            BinanceClientError::Server(error) => (error.status_code, error.data, error.headers),
+            BinanceClientError::Send(error) => {
+                return Self::NetworkError(format!("Failed to fetch: {error}"));
+            }
+            BinanceClientError::Parse(error) => {
+                return Self::ParseError(format!("Invalid request: {error}"));
+            }
+            BinanceClientError::InvalidApiSecret => {
+                return Self::ValidationError("Invalid API secret".to_string());
+            }
+        };
+        let retry_after = retry_after(&headers);
+
+        match serde_json::from_str::<BinanceErrorBody>(&body) {
+            Ok(error) =>
+                Self::BinanceError { status, code: error.code, msg: error.msg, retry_after },
+            Err(_) => Self::HttpError { status, body, retry_after },
+        }
+    }
+}
+
+/// Seconds of the `Retry-After` header, header names are lowercase in the connector
+fn retry_after(headers: &HashMap<String, String>) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER_HEADER)
+        .and_then(|seconds| seconds.trim().parse().ok())
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use binance_spot_connector_rust::http::error::HttpError;
+
+    use super::*;
+
+    fn binance_error(status: u16, code: i32) -> ApiError {
+        ApiError::BinanceError { status, code, msg: String::new(), retry_after: None }
+    }
+
+    #[test]
+    fn classifies_binance_errors() {
+        let timestamp = binance_error(400, -1021);
+        let rate_limit = binance_error(429, -1003);
+        let timeout = binance_error(503, -1007);
+        let banned = binance_error(418, -1003);
+        let filter_failure = binance_error(400, -1013);
+        let insufficient_balance = binance_error(400, -2010);
+
+        assert!(timestamp.is_unprocessed() && timestamp.is_retryable());
+        assert!(rate_limit.is_unprocessed() && rate_limit.is_retryable());
+        assert!(!timeout.is_unprocessed() && timeout.is_retryable());
+        assert!(!banned.is_retryable());
+        assert!(!filter_failure.is_retryable());
+        assert!(!insufficient_balance.is_retryable());
+        assert!(ApiError::NetworkError(String::new()).is_retryable());
+        assert!(!ApiError::ParseError(String::new()).is_retryable());
+    }
+
+    #[test]
+    fn only_transport_errors_are_network_errors() {
+        let headers = HashMap::from([(RETRY_AFTER_HEADER.to_string(), "3".to_string())]);
+        let overloaded = ApiError::from(
+            BinanceClientError::Server(HttpError::new(503, "Unavailable".to_string(), headers))
+        );
+        let invalid_secret = ApiError::from(BinanceClientError::InvalidApiSecret);
+
+        assert!(matches!(overloaded, ApiError::HttpError { status: 503, .. }));
+        assert!(overloaded.is_retryable());
+        assert_eq!(overloaded.retry_after(), Some(Duration::from_secs(3)));
+        assert!(matches!(invalid_secret, ApiError::ValidationError(_)));
+        assert!(!invalid_secret.is_retryable());
     }
 }
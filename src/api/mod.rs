@@ -1,4 +1,5 @@
 pub mod binance;
 pub mod error;
+pub mod paper;
 pub mod supported_api;
 pub mod client;
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+
+use serde::{ Deserialize, Serialize };
+use time::UtcDateTime;
+
+use crate::{
+    api::error::ApiError,
+    core::market::{ OrderExecution, OrderFill },
+    strategy::strategy::PaperTrading,
+};
+
+/// Market order to fill on the ledger around the current `market_price`
+pub struct MarketOrder<'a> {
+    pub symbol: &'a str,
+    pub quantity: f64,
+    pub market_price: f64,
+}
+
+/// Balances of a ledger kept across restarts of paper trading
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerState {
+    pub quote_balance: f64,
+    pub base_balances: HashMap<String, f64>,
+    pub last_order_id: u64,
+}
+
+/// Virtual account balances updated by simulated market fills, base holdings are kept
+/// per traded symbol
+#[derive(Debug)]
+pub struct Ledger {
+    quote_asset: String,
+    quote_balance: f64,
+    base_balances: HashMap<String, f64>,
+    fee_rate: f64,
+    slippage_rate: f64,
+    last_order_id: u64,
+}
+
+impl Ledger {
+    pub fn new(initial_balance: f64, quote_asset: &str, settings: &PaperTrading) -> Self {
+        Self {
+            quote_asset: quote_asset.to_string(),
+            quote_balance: initial_balance,
+            base_balances: settings.holdings.clone(),
+            fee_rate: f64::from(settings.fee) / 100_f64,
+            slippage_rate: f64::from(settings.slippage) / 100_f64,
+            last_order_id: 0,
+        }
+    }
+
+    /// Fills a market buy above `market_price` by the slippage, paying the fee in quote asset
+    pub fn buy(
+        &mut self,
+        order: MarketOrder<'_>,
+        timestamp: UtcDateTime
+    ) -> Result<OrderExecution, ApiError> {
+        let quantity = order.quantity;
+        let price = order.market_price * (1_f64 + self.slippage_rate);
+        let cost = quantity * price;
+        let commission = cost * self.fee_rate;
+
+        if cost + commission > self.quote_balance {
+            return Err(
+                ApiError::OrderError(
+                    format!(
+                        "Insufficient balance {} for order of {}",
+                        self.quote_balance,
+                        cost + commission
+                    )
+                )
+            );
+        }
+
+        self.quote_balance -= cost + commission;
+        *self.base_balances.entry(order.symbol.to_string()).or_default() += quantity;
+
+        let fill = self.fill(quantity, price, commission);
+
+        Ok(self.execution(fill, timestamp))
+    }
+
+    /// Fills a market sell below `market_price` by the slippage, paying the fee in quote asset
+    pub fn sell(
+        &mut self,
+        order: MarketOrder<'_>,
+        timestamp: UtcDateTime
+    ) -> Result<OrderExecution, ApiError> {
+        let quantity = order.quantity;
+        let base_balance = self.base_balance(order.symbol);
+
+        if quantity > base_balance {
+            return Err(
+                ApiError::OrderError(
+                    format!(
+                        "Insufficient {} holdings {} to sell {}",
+                        order.symbol,
+                        base_balance,
+                        quantity
+                    )
+                )
+            );
+        }
+
+        let price = order.market_price * (1_f64 - self.slippage_rate);
+        let proceeds = quantity * price;
+        let commission = proceeds * self.fee_rate;
+
+        self.base_balances.insert(order.symbol.to_string(), base_balance - quantity);
+        self.quote_balance += proceeds - commission;
+
+        let fill = self.fill(quantity, price, commission);
+
+        Ok(self.execution(fill, timestamp))
+    }
+
+    pub fn quote_balance(&self) -> f64 {
+        self.quote_balance
+    }
+
+    pub fn base_balance(&self, symbol: &str) -> f64 {
+        self.base_balances.get(symbol).copied().unwrap_or_default()
+    }
+
+    pub fn state(&self) -> LedgerState {
+        LedgerState {
+            quote_balance: self.quote_balance,
+            base_balances: self.base_balances.clone(),
+            last_order_id: self.last_order_id,
+        }
+    }
+
+    /// Replaces the balances with the ones left by a previous run, keeping the fee and
+    /// slippage of the current settings
+    pub fn restore(&mut self, state: LedgerState) {
+        self.quote_balance = state.quote_balance;
+        self.base_balances = state.base_balances;
+        self.last_order_id = state.last_order_id;
+    }
+
+    /// Quote balance plus the `symbol` holdings valued at `price`
+    pub fn equity(&self, symbol: &str, price: f64) -> f64 {
+        self.quote_balance + self.base_balance(symbol) * price
+    }
+
+    fn fill(&self, quantity: f64, price: f64, commission: f64) -> OrderFill {
+        OrderFill {
+            price,
+            quantity,
+            commission,
+            commission_asset: self.quote_asset.clone(),
+            trade_id: self.last_order_id + 1,
+        }
+    }
+
+    fn execution(&mut self, fill: OrderFill, timestamp: UtcDateTime) -> OrderExecution {
+        self.last_order_id += 1;
+
+        OrderExecution {
+            order_id: self.last_order_id,
+            executed_quantity: fill.quantity,
+            quote_quantity: fill.quantity * fill.price,
+            timestamp,
+            fills: vec![fill],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ledger(initial_balance: f64, fee: f32, slippage: f32) -> Ledger {
+        let settings = PaperTrading { initial_balance, fee, slippage, holdings: HashMap::new() };
+
+        Ledger::new(initial_balance, "USDT", &settings)
+    }
+
+    fn order(symbol: &str, quantity: f64, market_price: f64) -> MarketOrder<'_> {
+        MarketOrder { symbol, quantity, market_price }
+    }
+
+    #[test]
+    fn simulated_fills_apply_fee_and_slippage() {
+        let mut ledger = ledger(1000_f64, 0.1, 1.0);
+
+        let buy = ledger.buy(order("BTCUSDT", 1_f64, 100_f64), UtcDateTime::now()).unwrap();
+        assert!((buy.average_price() - 101_f64).abs() < 1e-6);
+        assert!((buy.commission_in("USDT") - 0.101).abs() < 1e-6);
+        assert!((ledger.quote_balance() - 898.899).abs() < 1e-6);
+        assert_eq!(ledger.base_balance("BTCUSDT"), 1_f64);
+
+        let sell = ledger.sell(order("BTCUSDT", 1_f64, 110_f64), UtcDateTime::now()).unwrap();
+        assert!((sell.average_price() - 108.9).abs() < 1e-6);
+        assert!((ledger.quote_balance() - (898.899 + 108.9 - 0.1089)).abs() < 1e-6);
+        assert_eq!(ledger.base_balance("BTCUSDT"), 0_f64);
+        assert_eq!(sell.order_id, 2);
+    }
+
+    #[test]
+    fn rejects_orders_above_balance() {
+        let mut ledger = ledger(100_f64, 0.0, 0.0);
+
+        assert!(ledger.buy(order("BTCUSDT", 2_f64, 100_f64), UtcDateTime::now()).is_err());
+        assert!(ledger.sell(order("BTCUSDT", 1_f64, 100_f64), UtcDateTime::now()).is_err());
+        assert_eq!(ledger.quote_balance(), 100_f64);
+    }
+
+    #[test]
+    fn keeps_holdings_per_symbol() {
+        let settings = PaperTrading {
+            initial_balance: 1000_f64,
+            fee: 0.0,
+            slippage: 0.0,
+            holdings: HashMap::from([("ETHUSDT".to_string(), 2_f64)]),
+        };
+        let mut ledger = Ledger::new(1000_f64, "USDT", &settings);
+
+        ledger.buy(order("BTCUSDT", 1_f64, 100_f64), UtcDateTime::now()).unwrap();
+
+        assert!(ledger.sell(order("BTCUSDT", 2_f64, 100_f64), UtcDateTime::now()).is_err());
+        assert!(ledger.sell(order("ETHUSDT", 2_f64, 10_f64), UtcDateTime::now()).is_ok());
+        assert_eq!(ledger.base_balance("BTCUSDT"), 1_f64);
+        assert_eq!(ledger.base_balance("ETHUSDT"), 0_f64);
+        assert_eq!(ledger.equity("BTCUSDT", 100_f64), 1020_f64);
+    }
+}
@@ -0,0 +1,144 @@
+use std::{ sync::{ Mutex, MutexGuard }, time::Duration };
+
+use async_trait::async_trait;
+use time::UtcDateTime;
+use tokio::sync::mpsc::Receiver;
+use tracing::{ error, info };
+
+use crate::{
+    api::{ client::{ ApiClient, KLineParams }, error::ApiError },
+    core::{ market::{ OrderExecution, ProcessedCandle }, position_store::PositionStore },
+    strategy::strategy::PaperTrading,
+};
+use ledger::{ Ledger, MarketOrder };
+
+pub mod ledger;
+
+/// `ApiClient` reading market data from another client and simulating fills on a virtual ledger
+pub struct PaperApi {
+    market_data: Box<dyn ApiClient>,
+    ledger: Mutex<Ledger>,
+    store: Option<PositionStore>,
+}
+
+impl PaperApi {
+    pub fn new(market_data: Box<dyn ApiClient>, quote_asset: &str, settings: &PaperTrading) -> Self {
+        info!(
+            "Paper trading enabled with virtual balance: {} {}",
+            settings.initial_balance,
+            quote_asset
+        );
+
+        Self {
+            market_data,
+            ledger: Mutex::new(Ledger::new(settings.initial_balance, quote_asset, settings)),
+            store: None,
+        }
+    }
+
+    /// Persists the ledger after every fill, so positions restored after a restart can
+    /// still be sold
+    pub fn with_store(mut self, store: PositionStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    fn lock_ledger(&self) -> MutexGuard<'_, Ledger> {
+        self.ledger.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn persist_ledger(&self, ledger: &Ledger) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save_ledger(&ledger.state()) {
+                error!("Failed to persist paper ledger: {}", e);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ApiClient for PaperApi {
+    async fn get_candles(&self, params: KLineParams) -> Result<Vec<ProcessedCandle>, ApiError> {
+        self.market_data.get_candles(params).await
+    }
+
+    async fn get_latest_candle(
+        &self,
+        symbol: &'_ str,
+        interval: &'_ Duration
+    ) -> Result<ProcessedCandle, ApiError> {
+        self.market_data.get_latest_candle(symbol, interval).await
+    }
+
+    async fn get_latest_price(&self, symbol: &'_ str) -> Result<f64, ApiError> {
+        self.market_data.get_latest_price(symbol).await
+    }
+
+    async fn get_server_time(&self) -> Result<u64, ApiError> {
+        self.market_data.get_server_time().await
+    }
+
+    async fn place_order_to_buy(
+        &self,
+        symbol: &'_ str,
+        quantity: f64,
+        _price: f64
+    ) -> Result<OrderExecution, ApiError> {
+        let market_price = self.get_latest_price(symbol).await?;
+        let order = MarketOrder { symbol, quantity, market_price };
+        let mut ledger = self.lock_ledger();
+        let execution = ledger.buy(order, UtcDateTime::now())?;
+        self.persist_ledger(&ledger);
+
+        info!("Paper order to buy filled for {} {}: {:?}", symbol, quantity, execution);
+
+        Ok(execution)
+    }
+
+    async fn place_order_to_sell(
+        &self,
+        symbol: &'_ str,
+        quantity: f64,
+        _price: f64
+    ) -> Result<OrderExecution, ApiError> {
+        let market_price = self.get_latest_price(symbol).await?;
+        let order = MarketOrder { symbol, quantity, market_price };
+        let mut ledger = self.lock_ledger();
+        let execution = ledger.sell(order, UtcDateTime::now())?;
+        self.persist_ledger(&ledger);
+
+        info!("Paper order to sell filled for {} {}: {:?}", symbol, quantity, execution);
+
+        Ok(execution)
+    }
+
+    async fn get_account_balance(&self, _symbol: &'_ str) -> Result<f64, ApiError> {
+        Ok(self.lock_ledger().quote_balance())
+    }
+
+    async fn load_trading_rules(&self, symbol: &'_ str) -> Result<(), ApiError> {
+        self.market_data.load_trading_rules(symbol).await
+    }
+
+    async fn restore_state(&self) -> Result<(), ApiError> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+
+        if let Some(state) = store.load_ledger()? {
+            info!("Restored paper ledger with balance: {}", state.quote_balance);
+
+            self.lock_ledger().restore(state);
+        }
+
+        Ok(())
+    }
+
+    async fn subscribe_candles(
+        &self,
+        symbol: &'_ str,
+        interval: &'_ Duration
+    ) -> Result<Option<Receiver<ProcessedCandle>>, ApiError> {
+        self.market_data.subscribe_candles(symbol, interval).await
+    }
+}
@@ -2,16 +2,48 @@ use serde::{ Serialize, Deserialize };
 
 use crate::api::{ binance::BinanceApi, client::ApiClient };
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
 #[serde(rename_all = "lowercase")]
 pub enum Api {
     Binance,
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum MarketDataSource {
+    #[default]
+    Stream,
+    Polling,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum TradingMode {
+    #[default]
+    Live,
+    Paper,
+}
+
+/// Exchange deployment the api connects to, `testnet` trades with fake funds
+#[derive(Debug, Serialize, Deserialize, PartialEq, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ExchangeEnvironment {
+    #[default]
+    Mainnet,
+    Testnet,
+}
+
 impl Api {
-    pub fn get_client(&self) -> impl ApiClient {
+    pub fn get_client(&self, environment: ExchangeEnvironment) -> impl ApiClient {
+        match &self {
+            Api::Binance => BinanceApi::new(environment),
+        }
+    }
+
+    /// Client reading market data without the credentials of the trading client
+    pub fn get_market_data_client(&self, environment: ExchangeEnvironment) -> impl ApiClient {
         match &self {
-            Api::Binance => BinanceApi::new(),
+            Api::Binance => BinanceApi::market_data(environment),
         }
     }
 }
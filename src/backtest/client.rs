@@ -0,0 +1,261 @@
+use std::{ sync::{ Arc, Mutex, MutexGuard }, time::Duration };
+
+use async_trait::async_trait;
+use rust_decimal::{ prelude::{ FromPrimitive, ToPrimitive }, Decimal };
+use time::UtcDateTime;
+use tracing::debug;
+
+use crate::{
+    api::{ client::{ ApiClient, KLineParams }, error::ApiError, paper::ledger::{ Ledger, MarketOrder } },
+    core::{ market::{ OrderExecution, ProcessedCandle }, math::{ Math, TradingMath } },
+};
+use super::report::{ BacktestReport, Trade };
+
+/// `ApiClient` replaying a recorded candle series and simulating market fills at candle close
+#[derive(Clone)]
+pub struct BacktestApi {
+    state: Arc<Mutex<BacktestState>>,
+}
+
+struct BacktestState {
+    symbol: String,
+    candles: Vec<ProcessedCandle>,
+    /// Duration of a replayed candle, fills are stamped with the close time of their candle
+    interval: Duration,
+    cursor: usize,
+    ledger: Ledger,
+    open_lots: Vec<Lot>,
+    trades: Vec<Trade>,
+}
+
+struct Lot {
+    timestamp: u64,
+    price: f64,
+    quantity: f64,
+    commission: f64,
+}
+
+impl BacktestApi {
+    pub fn new(
+        symbol: String,
+        candles: Vec<ProcessedCandle>,
+        interval: Duration,
+        ledger: Ledger
+    ) -> Self {
+        Self {
+            state: Arc::new(
+                Mutex::new(BacktestState {
+                    symbol,
+                    candles,
+                    interval,
+                    cursor: 0,
+                    ledger,
+                    open_lots: Vec::new(),
+                    trades: Vec::new(),
+                })
+            ),
+        }
+    }
+
+    pub fn has_next_candle(&self) -> bool {
+        let state = self.lock();
+
+        state.cursor < state.candles.len()
+    }
+
+    /// Quote balance plus base holdings valued at the last replayed close
+    pub fn equity(&self) -> f64 {
+        let state = self.lock();
+
+        state.ledger.equity(&state.symbol, state.current_price())
+    }
+
+    pub fn report(&self, initial_balance: f64, equity_curve: &[f64]) -> BacktestReport {
+        let state = self.lock();
+        let equity_curve: Vec<Decimal> = equity_curve
+            .iter()
+            .filter_map(|equity| Decimal::from_f64(*equity))
+            .collect();
+
+        BacktestReport {
+            symbol: state.symbol.clone(),
+            initial_balance,
+            final_equity: state.ledger.equity(&state.symbol, state.current_price()),
+            trades: state.trades.clone(),
+            open_positions: state.open_lots.len(),
+            max_drawdown: TradingMath::calculate_max_drawdown(&equity_curve)
+                .to_f64()
+                .unwrap_or_default(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BacktestState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl BacktestState {
+    fn current_candle(&self) -> Option<&ProcessedCandle> {
+        self.cursor.checked_sub(1).and_then(|index| self.candles.get(index))
+    }
+
+    fn current_price(&self) -> f64 {
+        self.current_candle().map_or(0_f64, |candle| candle.close)
+    }
+
+    fn current_timestamp(&self) -> u64 {
+        self.current_candle().map_or(0, |candle| candle.timestamp)
+    }
+
+    /// Close time of the current candle, the time its orders are filled at
+    fn current_time(&self) -> Result<UtcDateTime, ApiError> {
+        let timestamp = self.current_timestamp() + self.interval.as_secs();
+
+        UtcDateTime::from_unix_timestamp(timestamp as i64).map_err(|e|
+            ApiError::ParseError(format!("Invalid candle timestamp {timestamp}: {e}"))
+        )
+    }
+
+    fn take_lot(&mut self, quantity: f64) -> Option<Lot> {
+        if self.open_lots.is_empty() {
+            return None;
+        }
+
+        let index = self.open_lots
+            .iter()
+            .position(|lot| lot.quantity == quantity)
+            .unwrap_or(0);
+
+        Some(self.open_lots.remove(index))
+    }
+}
+
+#[async_trait]
+impl ApiClient for BacktestApi {
+    /// Serves the warmup candles and rewinds the replay to the first candle after them,
+    /// requests for a time range are answered from the candles replayed so far
+    async fn get_candles(&self, params: KLineParams) -> Result<Vec<ProcessedCandle>, ApiError> {
+        let mut state = self.lock();
+        let warmup_count = params.candles_count();
+
+        if let (Some(start_time), end_time) = params.time_range() {
+            let end_time = end_time.unwrap_or(u64::MAX);
+
+            return Ok(
+                state.candles[..state.cursor]
+                    .iter()
+                    .filter(|candle| (start_time..=end_time).contains(&(candle.timestamp * 1000)))
+                    .take(warmup_count)
+                    .cloned()
+                    .collect()
+            );
+        }
+
+        if state.candles.len() <= warmup_count {
+            return Err(
+                ApiError::ValidationError(
+                    format!(
+                        "Backtest requires more than {warmup_count} candles, got {}",
+                        state.candles.len()
+                    )
+                )
+            );
+        }
+
+        state.cursor = warmup_count;
+
+        Ok(state.candles[..warmup_count].to_vec())
+    }
+
+    async fn get_latest_candle(
+        &self,
+        _symbol: &'_ str,
+        _interval: &'_ Duration
+    ) -> Result<ProcessedCandle, ApiError> {
+        let mut state = self.lock();
+
+        let candle = state.candles
+            .get(state.cursor)
+            .cloned()
+            .ok_or(ApiError::NotFound("Candle series is exhausted".to_string()))?;
+
+        state.cursor += 1;
+
+        Ok(candle)
+    }
+
+    /// Close of the current candle, the price its orders are filled at
+    async fn get_latest_price(&self, _symbol: &'_ str) -> Result<f64, ApiError> {
+        Ok(self.lock().current_price())
+    }
+
+    async fn place_order_to_buy(
+        &self,
+        symbol: &'_ str,
+        quantity: f64,
+        price: f64
+    ) -> Result<OrderExecution, ApiError> {
+        let mut state = self.lock();
+        let market_price = state.current_price();
+        let time = state.current_time()?;
+        let execution = state.ledger.buy(MarketOrder { symbol, quantity, market_price }, time)?;
+        let timestamp = state.current_timestamp();
+        let commission = execution.fills
+            .iter()
+            .map(|fill| fill.commission)
+            .sum();
+
+        state.open_lots.push(Lot {
+            timestamp,
+            price: execution.average_price(),
+            quantity: execution.executed_quantity,
+            commission,
+        });
+
+        debug!(
+            "Simulated buy of {} {} at {} (requested {})",
+            quantity,
+            symbol,
+            execution.average_price(),
+            price
+        );
+
+        Ok(execution)
+    }
+
+    async fn place_order_to_sell(
+        &self,
+        symbol: &'_ str,
+        quantity: f64,
+        _price: f64
+    ) -> Result<OrderExecution, ApiError> {
+        let mut state = self.lock();
+        let market_price = state.current_price();
+        let time = state.current_time()?;
+        let execution = state.ledger.sell(MarketOrder { symbol, quantity, market_price }, time)?;
+        let exit_timestamp = state.current_timestamp();
+        let exit_commission: f64 = execution.fills
+            .iter()
+            .map(|fill| fill.commission)
+            .sum();
+
+        if let Some(lot) = state.take_lot(quantity) {
+            state.trades.push(Trade {
+                entry_timestamp: lot.timestamp,
+                exit_timestamp,
+                entry_price: lot.price,
+                exit_price: execution.average_price(),
+                quantity,
+                commission: lot.commission + exit_commission,
+            });
+        }
+
+        debug!("Simulated sell of {} {} at {}", quantity, symbol, execution.average_price());
+
+        Ok(execution)
+    }
+
+    async fn get_account_balance(&self, _symbol: &'_ str) -> Result<f64, ApiError> {
+        Ok(self.lock().ledger.quote_balance())
+    }
+}
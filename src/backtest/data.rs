@@ -0,0 +1,124 @@
+use std::{ borrow::Cow, fs, path::Path };
+
+use time::UtcDateTime;
+
+use crate::{
+    api::{ binance::response::BinanceResponse, error::ApiError },
+    core::market::ProcessedCandle,
+};
+
+const CSV_KLINE_COLUMNS: usize = 11;
+
+/// Loads a recorded candle series from a `.json` file with the Binance klines response body
+/// or from a `.csv` file in the Binance kline dump layout (open time in milliseconds)
+pub fn load_candles(path: &Path) -> Result<Vec<ProcessedCandle>, ApiError> {
+    let content = fs
+        ::read_to_string(path)
+        .map_err(|e| ApiError::NotFound(format!("Failed to read {}: {e}", path.display())))?;
+
+    let klines = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => BinanceResponse::deserialize_response(Cow::from(content))?,
+        Some("csv") => parse_csv(&content)?,
+        _ => {
+            return Err(
+                ApiError::ValidationError(
+                    format!("Unsupported candles file {}, expected .csv or .json", path.display())
+                )
+            );
+        }
+    };
+
+    let mut candles: Vec<ProcessedCandle> = klines.iter().map(ProcessedCandle::from).collect();
+    candles.sort_by_key(|candle| candle.timestamp);
+
+    Ok(candles)
+}
+
+fn parse_csv(content: &str) -> Result<Vec<BinanceResponse>, ApiError> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter(|(index, line)| !(*index == 0 && is_header(line)))
+        .map(|(index, line)| {
+            parse_csv_row(line).map_err(|reason|
+                ApiError::ParseError(format!("Invalid candle at line {}: {reason}", index + 1))
+            )
+        })
+        .collect()
+}
+
+fn is_header(line: &str) -> bool {
+    line.split(',')
+        .next()
+        .is_some_and(|column| column.trim().parse::<u64>().is_err())
+}
+
+fn parse_csv_row(line: &str) -> Result<BinanceResponse, String> {
+    let columns: Vec<&str> = line
+        .split(',')
+        .map(|column| column.trim())
+        .collect();
+
+    if columns.len() < CSV_KLINE_COLUMNS {
+        return Err(
+            format!("expected at least {CSV_KLINE_COLUMNS} columns, got {}", columns.len())
+        );
+    }
+
+    Ok(BinanceResponse {
+        open_timestamp: parse_timestamp(columns[0])?,
+        open_price: parse_float(columns[1])?,
+        high_price: parse_float(columns[2])?,
+        low_price: parse_float(columns[3])?,
+        close_price: parse_float(columns[4])?,
+        volume: parse_float(columns[5])?,
+        close_timestamp: parse_timestamp(columns[6])?,
+        quote_asset_vol: parse_float(columns[7])?,
+        num_of_trades: columns[8].parse().map_err(|e| format!("{e}"))?,
+        taker_buy_base_asset_vol: parse_float(columns[9])?,
+        taker_buy_quote_asset_vol: parse_float(columns[10])?,
+    })
+}
+
+fn parse_timestamp(value: &str) -> Result<UtcDateTime, String> {
+    let milliseconds: u64 = value.parse().map_err(|e| format!("{e}"))?;
+
+    UtcDateTime::from_unix_timestamp_nanos(i128::from(milliseconds) * 1_000_000).map_err(|e|
+        format!("{e}")
+    )
+}
+
+fn parse_float(value: &str) -> Result<f64, String> {
+    value.parse().map_err(|e| format!("{e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_csv_klines() {
+        let content =
+            r#"open_time,open,high,low,close,volume,close_time,quote_volume,count,taker_buy_volume,taker_buy_quote_volume,ignore
+1700000000000,100.5,101,99.5,100.8,12.5,1700001799999,1260,42,6.5,655,0
+
+1700001800000,100.8,102,100,101.9,8,1700003599999,810,30,4,405,0
+"#;
+
+        let klines = parse_csv(content).unwrap();
+        let candles: Vec<ProcessedCandle> = klines.iter().map(ProcessedCandle::from).collect();
+
+        assert_eq!(candles.len(), 2);
+        assert_eq!(candles[0].timestamp, 1_700_000_000);
+        assert_eq!(candles[0].close, 100.8);
+        assert_eq!(candles[1].high, 102_f64);
+        assert_eq!(candles[1].volume, 8_f64);
+    }
+
+    #[test]
+    fn parse_invalid_csv_klines() {
+        assert!(parse_csv("1700000000000,100.5,101,99.5").is_err());
+        assert!(parse_csv("1700000000000,abc,101,99.5,100.8,12.5,1700001799999,1260,42,6.5,655").is_err());
+    }
+}
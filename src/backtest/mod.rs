@@ -0,0 +1,63 @@
+use std::path::Path;
+
+use tracing::{ error, info, warn };
+
+use crate::{
+    api::{ error::ApiError, paper::ledger::Ledger },
+    core::bot::Bot,
+    strategy::{ strategy::Strategy, timeframe::duration_from_kline_interval },
+};
+use client::BacktestApi;
+use report::BacktestReport;
+
+pub mod client;
+pub mod data;
+pub mod report;
+
+/// Replays the candles from `candles_path` through the live `Bot` decision logic,
+/// filling orders with the fee and slippage from `[exchange.paper]`
+pub async fn run(
+    mut strategy: Strategy,
+    candles_path: &Path,
+    initial_balance: Option<f64>
+) -> Result<BacktestReport, ApiError> {
+    let candles = data::load_candles(candles_path)?;
+
+    if !strategy.markets.is_empty() {
+        warn!("Backtests replay a single symbol, ignoring [[markets]] of the strategy");
+        strategy.markets.clear();
+    }
+
+    if !strategy.pairs.is_empty() {
+        warn!("Backtests replay a single symbol, ignoring [[pairs]] of the strategy");
+        strategy.pairs.clear();
+    }
+
+    let interval = duration_from_kline_interval(&strategy.timeframe.interval);
+
+    if strategy.timeframe.tick != interval {
+        warn!("Backtests replay a single candle series, using the interval as tick");
+        strategy.timeframe.tick = interval;
+    }
+
+    info!("Starting backtest for {} over {} candles", strategy.symbol, candles.len());
+
+    let initial_balance = initial_balance.unwrap_or(strategy.exchange.paper.initial_balance);
+    let ledger = Ledger::new(initial_balance, &strategy.trading_symbol, &strategy.exchange.paper);
+    let api = BacktestApi::new(strategy.symbol.clone(), candles, interval, ledger);
+    let mut bot = Bot::with_client(strategy, Box::new(api.clone()));
+
+    bot.initialize().await?;
+
+    let mut equity_curve = vec![api.equity()];
+
+    while api.has_next_candle() {
+        if let Err(e) = bot.execute_trading_cycle().await {
+            error!("Error executing backtest cycle: {}", e);
+        }
+
+        equity_curve.push(api.equity());
+    }
+
+    Ok(api.report(initial_balance, &equity_curve))
+}
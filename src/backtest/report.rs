@@ -0,0 +1,154 @@
+use std::fmt::Display;
+
+use time::UtcDateTime;
+
+#[derive(Debug, Clone)]
+pub struct Trade {
+    pub entry_timestamp: u64,
+    pub exit_timestamp: u64,
+    pub entry_price: f64,
+    pub exit_price: f64,
+    pub quantity: f64,
+    pub commission: f64,
+}
+
+#[derive(Debug)]
+pub struct BacktestReport {
+    pub symbol: String,
+    pub initial_balance: f64,
+    pub final_equity: f64,
+    pub trades: Vec<Trade>,
+    pub open_positions: usize,
+    pub max_drawdown: f64,
+}
+
+impl Trade {
+    pub fn pnl(&self) -> f64 {
+        (self.exit_price - self.entry_price) * self.quantity - self.commission
+    }
+
+    pub fn pnl_percentage(&self) -> f64 {
+        (self.pnl() / (self.entry_price * self.quantity)) * 100_f64
+    }
+}
+
+impl BacktestReport {
+    pub fn pnl(&self) -> f64 {
+        self.final_equity - self.initial_balance
+    }
+
+    pub fn pnl_percentage(&self) -> f64 {
+        (self.pnl() / self.initial_balance) * 100_f64
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        if self.trades.is_empty() {
+            return 0_f64;
+        }
+
+        let winning_trades = self.trades
+            .iter()
+            .filter(|trade| trade.pnl() > 0_f64)
+            .count();
+
+        ((winning_trades as f64) / (self.trades.len() as f64)) * 100_f64
+    }
+}
+
+impl Display for BacktestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Backtest report for {}", self.symbol)?;
+        writeln!(f, "{:-<88}", "")?;
+        writeln!(
+            f,
+            "{:<22} {:<22} {:>12} {:>12} {:>10} {:>12}",
+            "entry",
+            "exit",
+            "entry price",
+            "exit price",
+            "pnl %",
+            "pnl"
+        )?;
+
+        for trade in &self.trades {
+            writeln!(
+                f,
+                "{:<22} {:<22} {:>12.2} {:>12.2} {:>10.2} {:>12.4}",
+                format_timestamp(trade.entry_timestamp),
+                format_timestamp(trade.exit_timestamp),
+                trade.entry_price,
+                trade.exit_price,
+                trade.pnl_percentage(),
+                trade.pnl()
+            )?;
+        }
+
+        writeln!(f, "{:-<88}", "")?;
+        writeln!(f, "Closed trades:   {}", self.trades.len())?;
+        writeln!(f, "Open positions:  {}", self.open_positions)?;
+        writeln!(f, "Initial balance: {:.2}", self.initial_balance)?;
+        writeln!(f, "Final equity:    {:.2}", self.final_equity)?;
+        writeln!(f, "PnL:             {:.2} ({:.2}%)", self.pnl(), self.pnl_percentage())?;
+        writeln!(f, "Win rate:        {:.2}%", self.win_rate())?;
+        write!(f, "Max drawdown:    {:.2}%", self.max_drawdown)
+    }
+}
+
+fn format_timestamp(timestamp: u64) -> String {
+    i64::try_from(timestamp)
+        .ok()
+        .and_then(|timestamp| UtcDateTime::from_unix_timestamp(timestamp).ok())
+        .map(|datetime| {
+            format!(
+                "{}-{:02}-{:02} {:02}:{:02}",
+                datetime.year(),
+                u8::from(datetime.month()),
+                datetime.day(),
+                datetime.hour(),
+                datetime.minute()
+            )
+        })
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(entry_price: f64, exit_price: f64) -> Trade {
+        Trade {
+            entry_timestamp: 0,
+            exit_timestamp: 60,
+            entry_price,
+            exit_price,
+            quantity: 2_f64,
+            commission: 0_f64,
+        }
+    }
+
+    #[test]
+    fn report_metrics() {
+        let report = BacktestReport {
+            symbol: "BTCUSDT".to_string(),
+            initial_balance: 1000_f64,
+            final_equity: 1050_f64,
+            trades: vec![trade(100_f64, 110_f64), trade(100_f64, 95_f64), trade(50_f64, 60_f64)],
+            open_positions: 0,
+            max_drawdown: 0_f64,
+        };
+
+        assert_eq!(report.trades[0].pnl(), 20_f64);
+        assert_eq!(report.trades[1].pnl_percentage(), -5_f64);
+        assert_eq!(report.pnl(), 50_f64);
+        assert_eq!(report.pnl_percentage(), 5_f64);
+        assert!((report.win_rate() - 66.666_666).abs() < 1e-4);
+    }
+
+    #[test]
+    fn trade_pnl_includes_commission() {
+        let trade = Trade { commission: 1_f64, ..trade(100_f64, 101_f64) };
+
+        assert_eq!(trade.pnl(), 1_f64);
+        assert_eq!(trade.pnl_percentage(), 0.5);
+    }
+}
@@ -1,160 +1,533 @@
-use std::time::Duration;
+use std::{ collections::HashMap, path::Path, time::Duration };
 
-use tokio::time::sleep;
-use tracing::{ debug, error, info };
+use time::UtcDateTime;
+use tokio::{ sync::mpsc::{ self, Receiver }, time::{ sleep, sleep_until, Instant } };
+use tracing::{ debug, error, info, warn };
 
 use crate::{
-    api::{ client::{ ApiClient, KLineParams }, error::ApiError },
-    strategy::{
-        mean_calculation::{ MaTracker, MeanCalculation },
-        strategy::Strategy,
-        timeframe::duration_from_kline_interval,
-    },
+    api::{ client::{ ApiClient, KLineParams }, error::ApiError, supported_api::MarketDataSource },
+    strategy::{ strategy::Strategy, timeframe::duration_from_kline_interval },
+};
+use super::{
+    circuit_breaker::CircuitBreaker,
+    market::ProcessedCandle,
+    pair_tracker::PairTracker,
+    position_store::PositionStore,
+    tracker::{ Indicators, SymbolTracker },
+    trading_strategy::{ CycleContext, PairCycleContext, TradingStrategy },
 };
-use super::{ market::ProcessedCandle, trading_strategy::TradingStrategy };
 
-const MA_PERIOD_DIFFERENCE: usize = 3;
 const TRADINC_CYCLE_RECOVERY_PERIOD: u64 = 30;
+const STREAM_STALL_GRACE_PERIOD: Duration = Duration::from_secs(30);
+/// Delay after a candle close before polling, giving the exchange time to finalize it
+const CANDLE_CLOSE_DELAY: Duration = Duration::from_secs(2);
+const MAX_BACKFILL_CANDLES: usize = 1000;
+pub const RESET_BREAKER_COMMAND: &str = "reset-breaker";
 
 pub struct Bot {
     strategy: Strategy,
     api_client: Box<dyn ApiClient>,
-    long_ma: MaTracker,
-    short_ma: MaTracker,
-    candles: Vec<ProcessedCandle>,
+    trackers: Vec<SymbolTracker>,
+    pair_trackers: Vec<PairTracker>,
     account_balance: f64,
     trading_strategy: TradingStrategy,
+    circuit_breaker: CircuitBreaker,
+    /// Latest close of every symbol, used to value the open positions
+    last_prices: HashMap<String, f64>,
 }
 
 impl Bot {
     pub fn new(strategy: Strategy) -> Self {
-        let long_period = strategy.timeframe.period_measurement.measure_bars;
-        let short_period = long_period / MA_PERIOD_DIFFERENCE;
-        let api_client = strategy.exchange.api.get_client();
+        let state_directory = strategy.state_directory();
+        let api_client = strategy.exchange.get_client(&strategy.trading_symbol, &state_directory);
+
+        Self::with_client(strategy, api_client).with_state_directory(&state_directory)
+    }
+
+    /// Persists the positions and the circuit breaker to `state_directory` and restores them
+    /// on initialization
+    pub fn with_state_directory(mut self, state_directory: &Path) -> Self {
+        self.trading_strategy = self.trading_strategy.with_position_store(
+            PositionStore::new(state_directory)
+        );
+        self.circuit_breaker = self.circuit_breaker.with_store(PositionStore::new(state_directory));
+        self
+    }
+
+    /// Clears a drawdown halt persisted by a previous run so the next run trades again
+    pub fn reset_circuit_breaker(strategy: &Strategy) -> Result<(), ApiError> {
+        CircuitBreaker::reset(&PositionStore::new(&strategy.state_directory()))
+    }
+
+    pub fn with_client(strategy: Strategy, api_client: Box<dyn ApiClient>) -> Self {
+        let interval = duration_from_kline_interval(&strategy.timeframe.interval);
 
         Self {
-            api_client: Box::new(api_client),
+            api_client,
             trading_strategy: TradingStrategy::new(strategy.risk_management.max_positions),
             account_balance: 0_f64,
-            candles: Vec::new(),
-            long_ma: MaTracker::new(
-                long_period,
-                strategy.timeframe.period_measurement.mean_calculation_method
-            ),
-            short_ma: MaTracker::new(
-                short_period,
-                strategy.timeframe.period_measurement.mean_calculation_method
-            ),
+            circuit_breaker: CircuitBreaker::new(strategy.risk_management.max_drawdown),
+            last_prices: HashMap::new(),
+            trackers: strategy.markets().into_iter().map(SymbolTracker::new).collect(),
+            pair_trackers: strategy.pairs
+                .iter()
+                .cloned()
+                .map(|pair| PairTracker::new(pair, interval, strategy.timeframe.tick))
+                .collect(),
             strategy,
         }
     }
 
     pub async fn initialize(&mut self) -> Result<(), ApiError> {
-        let candles = self.api_client.get_candles(
-            KLineParams::build(
-                self.strategy.timeframe.period_measurement.measure_bars * MA_PERIOD_DIFFERENCE,
-                self.strategy.symbol.clone(),
-                duration_from_kline_interval(&self.strategy.timeframe.interval)
-            )
-        ).await?;
+        let server_time = self.api_client.get_server_time().await?;
 
-        self.candles = candles
-            .into_iter()
-            .map(|candle| {
-                self.long_ma.update(candle.close);
+        for tracker in self.trackers.iter_mut() {
+            let symbol_strategy = &tracker.strategy;
+            let interval = duration_from_kline_interval(&symbol_strategy.timeframe.interval);
 
-                candle
-            })
-            .collect();
+            self.api_client.load_trading_rules(&symbol_strategy.symbol).await?;
 
-        let account_balance = self.api_client.get_account_balance(
-            &self.strategy.trading_symbol
-        ).await?;
+            let candles = self.api_client.get_candles(
+                KLineParams::build(tracker.history_size(), symbol_strategy.symbol.clone(), interval)
+            ).await?;
 
-        if let Some(latest_candles) = self.candles.chunks(MA_PERIOD_DIFFERENCE).last() {
-            for candle in latest_candles.iter() {
-                self.short_ma.update(candle.close);
-            }
+            tracker.seed(closed_candles(candles, interval, server_time));
+            prime_interval_candle(self.api_client.as_ref(), tracker, server_time).await?;
         }
 
-        self.candles.iter().for_each(|candle| {
-            self.long_ma.update(candle.close);
-        });
+        self.initialize_pairs(server_time).await?;
 
-        self.account_balance = account_balance;
+        self.api_client.restore_state().await?;
+        self.account_balance = self.api_client.get_account_balance(
+            &self.strategy.trading_symbol
+        ).await?;
 
-        info!("Bot initialized with {} candles", self.candles.len());
+        let restored_positions = self.trading_strategy.restore_positions()?;
+        self.circuit_breaker.restore()?;
 
-        debug!("Opening candles data: {:?}", self.candles);
+        if let Some(halted_at) = self.circuit_breaker.halted_at() {
+            error!(
+                "Trading was halted by the drawdown circuit breaker at {}, new entries stay \
+                 suspended until it is reset with the `{}` command",
+                halted_at,
+                RESET_BREAKER_COMMAND
+            );
+        }
 
         info!(
-            "Strating with {{short_ma: {}, long_ma: {} }}",
-            self.short_ma.calculate(),
-            self.long_ma.calculate()
+            "Bot initialized for {} symbols and {} pairs",
+            self.trackers.len(),
+            self.pair_trackers.len()
         );
+        info!("Restored {} open positions", restored_positions);
+
+        Ok(())
+    }
+
+    async fn initialize_pairs(&mut self, server_time: u64) -> Result<(), ApiError> {
+        let interval = duration_from_kline_interval(&self.strategy.timeframe.interval);
+
+        for pair_tracker in self.pair_trackers.iter_mut() {
+            let pair = &pair_tracker.pair;
+            let mut candles = Vec::with_capacity(2);
+
+            for symbol in [&pair.first_symbol, &pair.second_symbol] {
+                self.api_client.load_trading_rules(symbol).await?;
+
+                let symbol_candles = self.api_client.get_candles(
+                    KLineParams::build(pair.lookback, symbol.clone(), interval)
+                ).await?;
+
+                candles.push(closed_candles(symbol_candles, interval, server_time));
+            }
+
+            pair_tracker.seed(&candles[0], &candles[1]);
+        }
 
         Ok(())
     }
 
     pub async fn run(&mut self) -> Result<(), ApiError> {
-        info!("Starting bot for symbol: {}", self.strategy.symbol);
+        info!("Starting bot for symbols: {:?}", self.symbols());
 
         self.initialize().await?;
 
-        let mut interval = tokio::time::interval(self.strategy.timeframe.tick);
+        match self.subscribe_candles().await {
+            Some(candles) => self.run_streaming(candles).await,
+            None => self.run_polling().await,
+        }
+    }
+
+    /// Subscribes to candle streams of every symbol and merges them into a single channel
+    /// tagged with the tracker index
+    async fn subscribe_candles(&self) -> Option<Receiver<(usize, ProcessedCandle)>> {
+        if self.strategy.exchange.market_data != MarketDataSource::Stream {
+            return None;
+        }
+
+        let (sender, merged_candles) = mpsc::channel(self.trackers.len().max(1) * 16);
+
+        for (index, tracker) in self.trackers.iter().enumerate() {
+            let symbol_strategy = &tracker.strategy;
+
+            let mut candles = match
+                self.api_client.subscribe_candles(
+                    &symbol_strategy.symbol,
+                    &symbol_strategy.timeframe.tick
+                ).await
+            {
+                Ok(Some(candles)) => candles,
+                Ok(None) => {
+                    warn!("Candle streaming is not supported by the client, polling instead");
+                    return None;
+                }
+                Err(e) => {
+                    error!("Failed to subscribe to candle stream, polling instead: {}", e);
+                    return None;
+                }
+            };
+
+            let sender = sender.clone();
+
+            tokio::spawn(async move {
+                while let Some(candle) = candles.recv().await {
+                    if sender.send((index, candle)).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+
+        Some(merged_candles)
+    }
+
+    async fn run_streaming(
+        &mut self,
+        mut candles: Receiver<(usize, ProcessedCandle)>
+    ) -> Result<(), ApiError> {
+        let stall_timeout = self.shortest_tick() + STREAM_STALL_GRACE_PERIOD;
+        let mut stall_deadline = Instant::now() + stall_timeout;
+        let mut pairs_deadline = self.next_candle_close(self.strategy.timeframe.tick).await;
+        let has_pairs = !self.pair_trackers.is_empty();
 
         loop {
-            info!("Waiting for the next execution cycle");
+            info!("Waiting for the next closed candle");
+
+            let result = tokio::select! {
+                received = candles.recv() => match received {
+                    Some((index, candle)) => {
+                        stall_deadline = Instant::now() + stall_timeout;
+                        self.process_closed_candle(index, candle).await
+                    }
+                    None => {
+                        warn!("Candle stream closed, falling back to polling");
+                        return self.run_polling().await;
+                    }
+                },
+                _ = sleep_until(stall_deadline) => {
+                    warn!("No candle received from stream in {:?}, polling", stall_timeout);
+                    stall_deadline = Instant::now() + stall_timeout;
+                    self.poll_closed_candles().await
+                }
+                _ = sleep_until(pairs_deadline), if has_pairs => {
+                    pairs_deadline = self.next_candle_close(self.strategy.timeframe.tick).await;
+                    self.execute_pairs_cycle().await
+                }
+            };
+
+            if let Err(e) = result {
+                error!("Error executing trading cycle: {}", e);
+                sleep(Duration::from_secs(TRADINC_CYCLE_RECOVERY_PERIOD)).await;
+            }
+        }
+    }
+
+    /// Polls the closed candles of every symbol and pair, waking up just after the next
+    /// `tick` candle of any symbol closes on the exchange clock
+    async fn run_polling(&mut self) -> Result<(), ApiError> {
+        let mut last_pairs_candle: Option<u64> = None;
 
-            if let Err(e) = self.execute_trading_cycle().await {
+        loop {
+            if let Err(e) = self.poll_closed_candles().await {
                 error!("Error executing trading cycle: {}", e);
                 sleep(Duration::from_secs(TRADINC_CYCLE_RECOVERY_PERIOD)).await;
             }
 
-            interval.tick().await;
+            let server_time = self.server_time().await;
+            let pairs_candle = latest_closed_candle(server_time, self.strategy.timeframe.tick);
+
+            if last_pairs_candle.is_none_or(|last_candle| last_candle < pairs_candle) {
+                last_pairs_candle = Some(pairs_candle);
+
+                if let Err(e) = self.execute_pairs_cycle().await {
+                    error!("Error executing pairs trading cycle: {}", e);
+                }
+            }
+
+            let next_close = self.next_candle_close(self.shortest_tick()).await;
+
+            info!("Waiting for the next candle close");
+
+            sleep_until(next_close).await;
+        }
+    }
+
+    /// Runs a trading cycle for every symbol with a `tick` candle closed since its last cycle
+    pub async fn poll_closed_candles(&mut self) -> Result<(), ApiError> {
+        let server_time = self.server_time().await;
+        let mut result = Ok(());
+
+        for index in 0..self.trackers.len() {
+            let tracker = &self.trackers[index];
+            let latest_candle = latest_closed_candle(server_time, tracker.strategy.timeframe.tick);
+
+            if tracker.last_candle_timestamp().is_some_and(|last| last >= latest_candle) {
+                continue;
+            }
+
+            if let Err(e) = self.poll_symbol_cycle(index).await {
+                error!(
+                    "Error executing trading cycle for {}: {}",
+                    self.trackers[index].strategy.symbol,
+                    e
+                );
+                result = Err(e);
+            }
+        }
+
+        result
+    }
+
+    /// Exchange time in milliseconds, falling back to the local clock when it cannot be fetched
+    async fn server_time(&self) -> u64 {
+        match self.api_client.get_server_time().await {
+            Ok(server_time) => server_time,
+            Err(e) => {
+                warn!("Failed to get the server time, using the local clock: {}", e);
+                u64::try_from(UtcDateTime::now().unix_timestamp() * 1000).unwrap_or_default()
+            }
         }
     }
 
-    async fn execute_trading_cycle(&mut self) -> Result<(), ApiError> {
-        info!("Starting trading cycle with balance: {}", self.account_balance);
+    /// Instant just after the next candle of `tick` closes on the exchange clock
+    async fn next_candle_close(&self, tick: Duration) -> Instant {
+        let server_time = self.server_time().await;
+
+        Instant::now() + time_to_candle_close(server_time, tick) + CANDLE_CLOSE_DELAY
+    }
+
+    /// Polls the closed candles of every symbol and pair and runs a trading cycle for each
+    /// of them
+    pub async fn execute_trading_cycle(&mut self) -> Result<(), ApiError> {
+        let result = self.poll_closed_candles().await;
+
+        self.execute_pairs_cycle().await.and(result)
+    }
+
+    /// Polls the latest candles of every pair and trades their spread
+    async fn execute_pairs_cycle(&mut self) -> Result<(), ApiError> {
+        let mut result = Ok(());
+
+        for index in 0..self.pair_trackers.len() {
+            if let Err(e) = self.execute_pair_cycle(index).await {
+                error!(
+                    "Error executing trading cycle for pair {}: {}",
+                    self.pair_trackers[index].pair.name(),
+                    e
+                );
+                result = Err(e);
+            }
+        }
+
+        result
+    }
+
+    async fn execute_pair_cycle(&mut self, index: usize) -> Result<(), ApiError> {
+        let tick = self.strategy.timeframe.tick;
+        let pair = &self.pair_trackers[index].pair;
+
+        let first_candle = self.api_client.get_latest_candle(&pair.first_symbol, &tick).await?;
+        let second_candle = self.api_client.get_latest_candle(&pair.second_symbol, &tick).await?;
+
+        let is_liquidating = self.is_liquidating();
+        let pair_tracker = &mut self.pair_trackers[index];
+
+        let Some(signal) = pair_tracker.update(&first_candle, &second_candle) else {
+            return Ok(());
+        };
+
+        info!("Current pair {} signal: {:?}", pair_tracker.pair.name(), signal);
+
+        self.last_prices.insert(pair_tracker.pair.first_symbol.clone(), signal.first_price);
+        self.last_prices.insert(pair_tracker.pair.second_symbol.clone(), signal.second_price);
+
+        let context = PairCycleContext {
+            pair: &pair_tracker.pair,
+            signal,
+            allow_entries: !self.circuit_breaker.is_halted(),
+            account_balance: self.account_balance,
+            max_open_positions: self.strategy.risk_management.max_positions,
+            strategy: &self.strategy,
+            api_client: self.api_client.as_ref(),
+        };
+
+        let balance_difference = if is_liquidating {
+            self.trading_strategy.liquidate_pair(&context).await?
+        } else {
+            self.trading_strategy.check_pair_signals(&context).await?
+        };
+
+        self.update_balance(balance_difference);
+        self.update_circuit_breaker();
+
+        Ok(())
+    }
+
+    /// Polls the latest closed candle of a symbol, skipping it when already processed and
+    /// backfilling the candles missed by a late cycle
+    async fn poll_symbol_cycle(&mut self, index: usize) -> Result<(), ApiError> {
+        let symbol_strategy = &self.trackers[index].strategy;
 
         let latest_candle = self.api_client.get_latest_candle(
-            &self.strategy.symbol,
-            &self.strategy.timeframe.tick
+            &symbol_strategy.symbol,
+            &symbol_strategy.timeframe.tick
         ).await?;
-        let current_price = latest_candle.close;
 
-        info!("Received the latest candle: {:?}", latest_candle);
+        info!("Received the latest {} candle: {:?}", symbol_strategy.symbol, latest_candle);
 
-        self.insert_candle(latest_candle);
+        self.process_closed_candle(index, latest_candle).await
+    }
 
-        let short_ma = self.short_ma.update(current_price);
-        let long_ma = self.long_ma.update(current_price);
+    /// Processes a closed candle, skipping duplicates and backfilling candles missed while
+    /// the stream was disconnected or a cycle was late. Trading signals are only evaluated
+    /// on the latest candle
+    async fn process_closed_candle(
+        &mut self,
+        index: usize,
+        candle: ProcessedCandle
+    ) -> Result<(), ApiError> {
+        let Some(last_timestamp) = self.trackers[index].last_candle_timestamp() else {
+            return self.process_candle(index, candle).await;
+        };
+
+        if candle.timestamp <= last_timestamp {
+            debug!("Skipping already processed candle: {:?}", candle);
+            return Ok(());
+        }
 
-        info!("Updated MA: {{short: {}, long: {}}}", short_ma, long_ma);
+        let tick = self.trackers[index].strategy.timeframe.tick.as_secs();
 
-        let deviation =
-            ((self.short_ma.calculate() - self.long_ma.calculate()) / long_ma) * 100_f64;
-        info!("Current mean deviation: {}", deviation);
+        if candle.timestamp > last_timestamp + tick {
+            let missed_candles = self.get_missed_candles(
+                &self.trackers[index],
+                last_timestamp,
+                candle.timestamp
+            ).await?;
 
-        let balance_difference = self.trading_strategy.check_exit_signals(
-            current_price,
-            deviation,
-            &self.strategy,
-            self.api_client.as_ref()
+            // Signals of missed candles are stale, they only complete the history
+            for missed_candle in missed_candles {
+                info!("Backfilling candle: {:?}", missed_candle);
+
+                self.trackers[index].backfill(&missed_candle);
+            }
+        }
+
+        self.process_candle(index, candle).await
+    }
+
+    async fn get_missed_candles(
+        &self,
+        tracker: &SymbolTracker,
+        last_timestamp: u64,
+        next_timestamp: u64
+    ) -> Result<Vec<ProcessedCandle>, ApiError> {
+        let symbol_strategy = &tracker.strategy;
+        let tick = symbol_strategy.timeframe.tick.as_secs();
+        let missed_count = ((next_timestamp - last_timestamp) / tick).saturating_sub(1);
+
+        warn!("Detected {} missed {} candles, backfilling", missed_count, symbol_strategy.symbol);
+
+        let candles = self.api_client.get_candles(
+            KLineParams::build(
+                (missed_count as usize).min(MAX_BACKFILL_CANDLES),
+                symbol_strategy.symbol.clone(),
+                symbol_strategy.timeframe.tick
+            )
+                .start_time((last_timestamp + tick) * 1000)
+                .end_time(next_timestamp * 1000 - 1)
         ).await?;
 
-        self.update_balance(balance_difference);
+        Ok(
+            candles
+                .into_iter()
+                .filter(|candle| {
+                    candle.timestamp > last_timestamp && candle.timestamp < next_timestamp
+                })
+                .collect()
+        )
+    }
+
+    async fn process_candle(
+        &mut self,
+        index: usize,
+        latest_candle: ProcessedCandle
+    ) -> Result<(), ApiError> {
+        let is_liquidating = self.is_liquidating();
+        let tracker = &mut self.trackers[index];
+
+        info!(
+            "Starting {} trading cycle with balance: {}",
+            tracker.strategy.symbol,
+            self.account_balance
+        );
+
+        let current_price = latest_candle.close;
+        let candle_timestamp = latest_candle.timestamp;
+        let indicators = tracker.update(latest_candle);
+
+        log_indicators(&tracker.strategy.symbol, &indicators);
+        self.last_prices.insert(tracker.strategy.symbol.clone(), current_price);
 
-        let balance_difference = self.trading_strategy.check_entry_signals(
+        let exit_context = CycleContext {
             current_price,
-            deviation,
-            self.account_balance,
-            &self.strategy,
-            self.api_client.as_ref()
-        ).await?;
+            candle_timestamp,
+            indicators,
+            account_balance: self.account_balance,
+            max_open_positions: self.strategy.risk_management.max_positions,
+            strategy: &tracker.strategy,
+            api_client: self.api_client.as_ref(),
+        };
+
+        let balance_difference = if is_liquidating {
+            self.trading_strategy.liquidate_positions(&exit_context).await
+        } else {
+            self.trading_strategy.check_exit_signals(&exit_context).await?
+        };
 
         self.update_balance(balance_difference);
+        self.update_circuit_breaker();
+
+        let tracker = &self.trackers[index];
+
+        if self.circuit_breaker.is_halted() {
+            info!("Drawdown circuit breaker is halted, new entries are suspended");
+        } else if is_entry_regime(&tracker.strategy, &indicators) {
+            let balance_difference = self.trading_strategy.check_entry_signals(
+                &(CycleContext {
+                    current_price,
+                    candle_timestamp,
+                    indicators,
+                    account_balance: self.account_balance,
+                    max_open_positions: self.strategy.risk_management.max_positions,
+                    strategy: &tracker.strategy,
+                    api_client: self.api_client.as_ref(),
+                })
+            ).await?;
+
+            self.update_balance(balance_difference);
+        } else {
+            info!("{} is trending, new entries are suspended", tracker.strategy.symbol);
+        }
 
         info!(
             "Trading cycle executed, current balance: {}\nopen positions: {}",
@@ -165,19 +538,204 @@ impl Bot {
         Ok(())
     }
 
+    pub fn account_balance(&self) -> f64 {
+        self.account_balance
+    }
+
+    pub fn open_positions_count(&self) -> usize {
+        self.trading_strategy.open_positions_count()
+    }
+
+    fn symbols(&self) -> Vec<&str> {
+        self.trackers
+            .iter()
+            .map(|tracker| tracker.strategy.symbol.as_str())
+            .collect()
+    }
+
+    fn shortest_tick(&self) -> Duration {
+        self.trackers
+            .iter()
+            .map(|tracker| tracker.strategy.timeframe.tick)
+            .min()
+            .unwrap_or(self.strategy.timeframe.tick)
+    }
+
     fn update_balance(&mut self, sum: f64) {
         debug!("Updating balance with sum: {}", sum);
         self.account_balance += sum;
     }
 
-    fn insert_candle(&mut self, candle: ProcessedCandle) {
-        self.candles.push(candle);
+    /// Balance plus the market value of the open positions at the latest known prices
+    fn equity(&self) -> f64 {
+        self.account_balance +
+            self.trading_strategy.open_positions_value(|symbol| {
+                self.last_prices.get(symbol).copied()
+            })
+    }
 
-        if
-            self.candles.len() >
-            self.strategy.timeframe.period_measurement.measure_bars * MA_PERIOD_DIFFERENCE
-        {
-            self.candles.remove(0);
+    fn update_circuit_breaker(&mut self) {
+        let equity = self.equity();
+
+        if self.circuit_breaker.update(equity) {
+            error!(
+                "Equity {:.2} fell {:.2}% below its high-water mark, halting new entries until \
+                 the circuit breaker is reset with the `{}` command",
+                equity,
+                self.circuit_breaker.drawdown(equity),
+                RESET_BREAKER_COMMAND
+            );
         }
     }
+
+    fn is_liquidating(&self) -> bool {
+        self.circuit_breaker.is_halted() && self.strategy.risk_management.liquidate_on_halt
+    }
+}
+
+fn log_indicators(symbol: &str, indicators: &Indicators) {
+    info!(
+        "Current {} mean deviation: {}, z-score: {}",
+        symbol,
+        indicators.deviation,
+        indicators.z_score
+    );
+    debug!("Current {} Bollinger Bands: {:?}", symbol, indicators.bollinger_bands);
+
+    if let Some(rsi) = indicators.rsi {
+        info!("Current {} RSI: {:.2}", symbol, rsi);
+    }
+
+    if let Some(regime) = indicators.regime {
+        info!(
+            "Current {} regime: {{hurst: {:.2}, adf: {:.2}, half-life: {}}}",
+            symbol,
+            regime.hurst_exponent,
+            regime.adf_statistic,
+            regime.half_life.map_or("none".to_string(), |half_life| format!("{half_life:.1} bars"))
+        );
+    }
+}
+
+/// Regime gate, new entries are allowed unless the regime filter detects a trending series
+fn is_entry_regime(strategy: &Strategy, indicators: &Indicators) -> bool {
+    match (&strategy.regime_filter, indicators.regime) {
+        (Some(filter), Some(regime)) => regime.is_mean_reverting(filter),
+        _ => true,
+    }
+}
+
+/// Feeds the tracker the closed `tick` candles of the `interval` candle still forming
+async fn prime_interval_candle(
+    api_client: &dyn ApiClient,
+    tracker: &mut SymbolTracker,
+    server_time: u64
+) -> Result<(), ApiError> {
+    let timeframe = &tracker.strategy.timeframe;
+    let interval = duration_from_kline_interval(&timeframe.interval).as_secs();
+    let tick = timeframe.tick.as_secs().max(1);
+
+    let interval_start = (server_time / 1000 / interval.max(1)) * interval;
+    let tick_count = (server_time / 1000 - interval_start) / tick;
+
+    if interval <= tick || tick_count == 0 {
+        return Ok(());
+    }
+
+    let candles = api_client.get_candles(
+        KLineParams::build(tick_count as usize, tracker.strategy.symbol.clone(), timeframe.tick)
+            .start_time(interval_start * 1000)
+    ).await?;
+
+    tracker.prime(&closed_candles(candles, timeframe.tick, server_time));
+
+    Ok(())
+}
+
+/// Drops the candles of `interval` still forming at `server_time` milliseconds
+fn closed_candles(
+    candles: Vec<ProcessedCandle>,
+    interval: Duration,
+    server_time: u64
+) -> Vec<ProcessedCandle> {
+    candles
+        .into_iter()
+        .filter(|candle| (candle.timestamp + interval.as_secs()) * 1000 <= server_time)
+        .collect()
+}
+
+/// Open time in seconds of the latest candle of `tick` closed at `server_time` milliseconds
+fn latest_closed_candle(server_time: u64, tick: Duration) -> u64 {
+    let tick = tick.as_secs().max(1);
+
+    (server_time / 1000 / tick).saturating_sub(1) * tick
+}
+
+/// Time from `server_time` milliseconds until the running candle of `tick` closes
+fn time_to_candle_close(server_time: u64, tick: Duration) -> Duration {
+    let tick = u64::try_from(tick.as_millis()).unwrap_or(u64::MAX).max(1);
+
+    Duration::from_millis(tick - (server_time % tick))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ core::tracker::Regime, tests::strategy::strategy };
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    #[test]
+    fn aligns_to_candle_close_times() {
+        let server_time = (10 * 3600 + 15 * 60) * 1000;
+
+        assert_eq!(latest_closed_candle(server_time, HOUR), 9 * 3600);
+        assert_eq!(time_to_candle_close(server_time, HOUR), Duration::from_secs(45 * 60));
+        assert_eq!(time_to_candle_close(10 * 3600 * 1000, HOUR), HOUR);
+    }
+
+    #[test]
+    fn drops_forming_candles() {
+        let candle = |timestamp| ProcessedCandle {
+            timestamp,
+            open: 1_f64,
+            high: 1_f64,
+            low: 1_f64,
+            close: 1_f64,
+            volume: 1_f64,
+        };
+        let candles = vec![candle(8 * 3600), candle(9 * 3600), candle(10 * 3600)];
+
+        let closed = closed_candles(candles, HOUR, (10 * 3600 + 15 * 60) * 1000);
+
+        assert_eq!(closed.len(), 2);
+        assert_eq!(closed[1].timestamp, 9 * 3600);
+    }
+
+    #[test]
+    fn regime_filter_gates_trending_series() {
+        let filtered = strategy("[regime_filter]\nmax_hurst = 0.5\nadf_critical_value = -3");
+        let indicators = |regime| Indicators {
+            deviation: 0_f64,
+            mean: 100_f64,
+            z_score: 0_f64,
+            bollinger_bands: None,
+            rsi: None,
+            regime,
+            atr: None,
+            chandelier_exit: None,
+        };
+        let regime = |hurst_exponent, adf_statistic| Some(Regime {
+            hurst_exponent,
+            adf_statistic,
+            half_life: None,
+        });
+
+        assert!(is_entry_regime(&filtered, &indicators(regime(0.4, -3.5))));
+        assert!(!is_entry_regime(&filtered, &indicators(regime(0.6, -3.5))));
+        assert!(!is_entry_regime(&filtered, &indicators(regime(0.4, -2.5))));
+        // Entries are allowed until the lookback is filled, and without a regime filter
+        assert!(is_entry_regime(&filtered, &indicators(None)));
+        assert!(is_entry_regime(&strategy(""), &indicators(regime(0.6, -2.5))));
+    }
 }
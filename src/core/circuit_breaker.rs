@@ -0,0 +1,140 @@
+use serde::{ Deserialize, Serialize };
+use time::UtcDateTime;
+use tracing::{ error, info };
+
+use crate::api::error::ApiError;
+use super::position_store::PositionStore;
+
+/// Persisted state of the drawdown circuit breaker
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BreakerState {
+    /// Highest equity seen since the last reset
+    pub high_water_mark: f64,
+    /// Time trading was halted at, `None` while trading
+    pub halted_at: Option<UtcDateTime>,
+}
+
+/// Halts new entries once the equity falls `max_drawdown` percent below its high-water mark,
+/// trading resumes only after an explicit reset
+pub struct CircuitBreaker {
+    max_drawdown: f64,
+    state: BreakerState,
+    store: Option<PositionStore>,
+}
+
+impl CircuitBreaker {
+    pub fn new(max_drawdown: f32) -> Self {
+        Self {
+            max_drawdown: f64::from(max_drawdown),
+            state: BreakerState::default(),
+            store: None,
+        }
+    }
+
+    pub fn with_store(mut self, store: PositionStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Loads the state left by a previous run from the store
+    pub fn restore(&mut self) -> Result<(), ApiError> {
+        if let Some(store) = &self.store {
+            self.state = store.load_breaker_state()?;
+        }
+
+        Ok(())
+    }
+
+    /// Clears a halt persisted in `store`, the high-water mark starts over from the next equity
+    pub fn reset(store: &PositionStore) -> Result<(), ApiError> {
+        let state = store.load_breaker_state()?;
+
+        match state.halted_at {
+            Some(halted_at) => info!("Resetting circuit breaker halted at {}", halted_at),
+            None => info!("Circuit breaker is not halted, resetting the high-water mark"),
+        }
+
+        store.save_breaker_state(&BreakerState::default())
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.state.halted_at.is_some()
+    }
+
+    pub fn halted_at(&self) -> Option<UtcDateTime> {
+        self.state.halted_at
+    }
+
+    /// Drawdown of `equity` from the high-water mark in percent
+    pub fn drawdown(&self, equity: f64) -> f64 {
+        if self.state.high_water_mark <= 0_f64 {
+            return 0_f64;
+        }
+
+        (((self.state.high_water_mark - equity) / self.state.high_water_mark) * 100_f64).max(
+            0_f64
+        )
+    }
+
+    /// Records the current equity, returning `true` when it halted trading
+    pub fn update(&mut self, equity: f64) -> bool {
+        if self.is_halted() {
+            return false;
+        }
+
+        if equity > self.state.high_water_mark {
+            self.state.high_water_mark = equity;
+            self.persist();
+
+            return false;
+        }
+
+        if self.drawdown(equity) < self.max_drawdown {
+            return false;
+        }
+
+        self.state.halted_at = Some(UtcDateTime::now());
+        self.persist();
+
+        true
+    }
+
+    fn persist(&self) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save_breaker_state(&self.state) {
+                error!("Failed to persist circuit breaker state: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn halts_below_high_water_mark_until_reset() {
+        let directory = tempfile::tempdir().unwrap();
+        let store = || PositionStore::new(directory.path());
+        let mut breaker = CircuitBreaker::new(10.0).with_store(store());
+
+        assert!(!breaker.update(1000_f64));
+        assert!(!breaker.update(1200_f64));
+        assert!(!breaker.update(1100_f64));
+        assert!(breaker.update(1080_f64));
+        assert!(!breaker.update(1500_f64));
+        assert!(breaker.is_halted());
+
+        let mut restored = CircuitBreaker::new(10.0).with_store(store());
+        restored.restore().unwrap();
+
+        assert!(restored.is_halted());
+        assert_eq!(restored.drawdown(900_f64), 25_f64);
+
+        CircuitBreaker::reset(&store()).unwrap();
+        restored.restore().unwrap();
+
+        assert!(!restored.is_halted());
+        assert!(!restored.update(900_f64));
+    }
+}
@@ -1,15 +1,48 @@
+use std::time::Duration;
+
+use serde::{ Deserialize, Serialize };
 use time::UtcDateTime;
 use uuid::Uuid;
 
-use crate::api::binance::response::BinanceResponse;
+use crate::api::binance::response::{ BinanceResponse, OrderResponse };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Position {
     pub id: Uuid,
     pub symbol: String,
     pub entry_price: f64,
     pub quantity: f64,
     pub timestamp: UtcDateTime,
+    #[serde(default)]
+    pub entry_order: Option<OrderExecution>,
+    /// Highest close observed since the position was opened
+    #[serde(default)]
+    pub highest_price: f64,
+    /// Stop the `atr` position sizing risked `risk_per_trade` against, `None` for other sizings
+    #[serde(default)]
+    pub stop_price: Option<f64>,
+    /// Quote asset spent on entry, including the commission paid in quote
+    #[serde(default)]
+    pub entry_cost: f64,
+}
+
+/// Result of an executed market order as reported by the exchange
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderExecution {
+    pub order_id: u64,
+    pub executed_quantity: f64,
+    pub quote_quantity: f64,
+    pub timestamp: UtcDateTime,
+    pub fills: Vec<OrderFill>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderFill {
+    pub price: f64,
+    pub quantity: f64,
+    pub commission: f64,
+    pub commission_asset: String,
+    pub trade_id: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -24,7 +57,229 @@ pub struct ProcessedCandle {
 
 impl Position {
     pub fn new(symbol: String, entry_price: f64, quantity: f64, timestamp: UtcDateTime) -> Self {
-        Self { id: Uuid::new_v4(), symbol, entry_price, quantity, timestamp }
+        Self {
+            id: Uuid::new_v4(),
+            symbol,
+            entry_price,
+            quantity,
+            timestamp,
+            entry_order: None,
+            highest_price: entry_price,
+            stop_price: None,
+            entry_cost: entry_price * quantity,
+        }
+    }
+
+    /// Opens a position from a buy execution, holding the executed quantity minus
+    /// the commission charged in `base_asset` at the cost of the quote spent with the
+    /// commission charged in `quote_asset`
+    pub fn from_execution(
+        symbol: String,
+        execution: OrderExecution,
+        base_asset: &str,
+        quote_asset: &str
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            symbol,
+            entry_price: execution.average_price(),
+            quantity: execution.executed_quantity - execution.commission_in(base_asset),
+            timestamp: execution.timestamp,
+            highest_price: execution.average_price(),
+            stop_price: None,
+            entry_cost: execution.quote_quantity + execution.commission_in(quote_asset),
+            entry_order: Some(execution),
+        }
+    }
+
+    /// Highest price of the position, positions stored before peaks were tracked starting
+    /// from their entry price
+    pub fn peak_price(&self) -> f64 {
+        self.highest_price.max(self.entry_price)
+    }
+
+    /// Quote asset spent on entry, positions stored before the cost was tracked valued at
+    /// their entry price
+    pub fn cost(&self) -> f64 {
+        if self.entry_cost > 0_f64 { self.entry_cost } else { self.entry_price * self.quantity }
+    }
+}
+
+/// Outcome of a closed position, kept to estimate the win rate of the bot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClosedTrade {
+    pub position_id: Uuid,
+    pub symbol: String,
+    /// Quote asset spent on entry
+    pub cost: f64,
+    /// Quote asset received on exit, net of the commission
+    pub proceeds: f64,
+    pub timestamp: UtcDateTime,
+}
+
+impl ClosedTrade {
+    pub fn profit(&self) -> f64 {
+        self.proceeds - self.cost
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum LegSide {
+    /// Bought on entry and sold on exit
+    Long,
+    /// Sold from the held base asset on entry and bought back on exit
+    Short,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionLeg {
+    pub symbol: String,
+    pub side: LegSide,
+    pub entry_price: f64,
+    pub quantity: f64,
+}
+
+/// Opposite legs of a pairs trade accounted as one logical position, legs are removed
+/// once closed so a partially closed pair can be retried
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairPosition {
+    pub id: Uuid,
+    pub pair: String,
+    pub hedge_ratio: f64,
+    pub legs: Vec<PositionLeg>,
+    pub timestamp: UtcDateTime,
+}
+
+impl PositionLeg {
+    /// Opens a leg from its entry execution, a long leg holding the executed quantity minus
+    /// the commission charged in `base_asset`
+    pub fn from_execution(
+        symbol: String,
+        side: LegSide,
+        execution: &OrderExecution,
+        base_asset: &str
+    ) -> Self {
+        let quantity = match side {
+            LegSide::Long => execution.executed_quantity - execution.commission_in(base_asset),
+            LegSide::Short => execution.executed_quantity,
+        };
+
+        Self { symbol, side, entry_price: execution.average_price(), quantity }
+    }
+
+    pub fn pnl(&self, price: f64) -> f64 {
+        match self.side {
+            LegSide::Long => (price - self.entry_price) * self.quantity,
+            LegSide::Short => (self.entry_price - price) * self.quantity,
+        }
+    }
+
+    pub fn notional(&self) -> f64 {
+        self.entry_price * self.quantity
+    }
+}
+
+impl PairPosition {
+    /// Combined profit of the open legs in percent of their entry notional,
+    /// `price_of` returning the current price of a leg symbol
+    pub fn pnl_percentage(&self, price_of: impl Fn(&str) -> f64) -> f64 {
+        let notional: f64 = self.legs.iter().map(PositionLeg::notional).sum();
+
+        if notional == 0_f64 {
+            return 0_f64;
+        }
+
+        let pnl: f64 = self.legs
+            .iter()
+            .map(|leg| leg.pnl(price_of(&leg.symbol)))
+            .sum();
+
+        (pnl / notional) * 100_f64
+    }
+}
+
+impl OrderExecution {
+    pub fn average_price(&self) -> f64 {
+        if self.executed_quantity == 0_f64 {
+            return 0_f64;
+        }
+
+        self.quote_quantity / self.executed_quantity
+    }
+
+    pub fn commission_in(&self, asset: &str) -> f64 {
+        self.fills
+            .iter()
+            .filter(|fill| fill.commission_asset == asset)
+            .map(|fill| fill.commission)
+            .sum()
+    }
+}
+
+impl From<OrderResponse> for OrderExecution {
+    fn from(value: OrderResponse) -> Self {
+        Self {
+            order_id: value.order_id,
+            executed_quantity: value.executed_qty,
+            quote_quantity: value.cummulative_quote_qty,
+            timestamp: value.transact_time,
+            fills: value.fills
+                .into_iter()
+                .map(|fill| OrderFill {
+                    price: fill.price,
+                    quantity: fill.qty,
+                    commission: fill.commission,
+                    commission_asset: fill.commission_asset,
+                    trade_id: fill.trade_id,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Builds candles of `interval` from consecutive candles of a shorter `tick`
+#[derive(Debug)]
+pub struct CandleAggregator {
+    interval: u64,
+    tick: u64,
+    current: Option<ProcessedCandle>,
+}
+
+impl CandleAggregator {
+    pub fn new(interval: Duration, tick: Duration) -> Self {
+        Self {
+            interval: interval.as_secs().max(1),
+            tick: tick.as_secs().max(1),
+            current: None,
+        }
+    }
+
+    /// Adds a closed `tick` candle, returning the `interval` candles it completes, oldest
+    /// first. A candle opening a new interval also completes the previous one when its last
+    /// tick was missed
+    pub fn push(&mut self, candle: &ProcessedCandle) -> Vec<ProcessedCandle> {
+        let interval_start = (candle.timestamp / self.interval) * self.interval;
+        let mut completed = Vec::new();
+
+        match &mut self.current {
+            Some(current) if current.timestamp == interval_start => {
+                current.high = current.high.max(candle.high);
+                current.low = current.low.min(candle.low);
+                current.close = candle.close;
+                current.volume += candle.volume;
+            }
+            current => {
+                let interval_candle = ProcessedCandle { timestamp: interval_start, ..candle.clone() };
+
+                completed.extend(current.replace(interval_candle));
+            }
+        }
+
+        if candle.timestamp + self.tick >= interval_start + self.interval {
+            completed.extend(self.current.take());
+        }
+
+        completed
     }
 }
 
@@ -32,6 +287,10 @@ impl ProcessedCandle {
     pub fn calculate_mean(&self) -> f64 {
         (self.open + self.close) / 2_f64
     }
+
+    pub fn typical_price(&self) -> f64 {
+        (self.high + self.low + self.close) / 3_f64
+    }
 }
 
 impl From<BinanceResponse> for ProcessedCandle {
@@ -42,7 +301,7 @@ impl From<BinanceResponse> for ProcessedCandle {
             low: value.low_price,
             high: value.high_price,
             open: value.open_price,
-            volume: value.taker_buy_base_asset_vol,
+            volume: value.volume,
         }
     }
 }
@@ -55,7 +314,129 @@ impl From<&BinanceResponse> for ProcessedCandle {
             low: value.low_price,
             high: value.high_price,
             open: value.open_price,
-            volume: value.taker_buy_base_asset_vol,
+            volume: value.volume,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+
+    const FULL_ORDER_RESPONSE: &str =
+        r#"{
+    "symbol": "BTCUSDT",
+    "orderId": 28,
+    "orderListId": -1,
+    "clientOrderId": "6gCrw2kRUAF9CvJDGP16IP",
+    "transactTime": 1507725176595,
+    "price": "0.00000000",
+    "origQty": "0.00300000",
+    "executedQty": "0.00300000",
+    "cummulativeQuoteQty": "120.00300000",
+    "status": "FILLED",
+    "timeInForce": "GTC",
+    "type": "MARKET",
+    "side": "BUY",
+    "fills": [
+        { "price": "40000.00000000", "qty": "0.00200000", "commission": "0.00000200", "commissionAsset": "BTC", "tradeId": 56 },
+        { "price": "40003.00000000", "qty": "0.00100000", "commission": "0.04000300", "commissionAsset": "USDT", "tradeId": 57 }
+    ]
+}"#;
+
+    #[test]
+    fn position_from_full_order_response() {
+        let response = OrderResponse::deserialize_response(Cow::from(FULL_ORDER_RESPONSE)).unwrap();
+        let execution = OrderExecution::from(response);
+
+        assert_eq!(execution.order_id, 28);
+        assert_eq!(execution.fills.len(), 2);
+        assert!((execution.average_price() - 40001_f64).abs() < 1e-9);
+        assert!((execution.commission_in("USDT") - 0.040003).abs() < 1e-12);
+
+        let position = Position::from_execution("BTCUSDT".to_string(), execution, "BTC", "USDT");
+
+        assert!((position.entry_price - 40001_f64).abs() < 1e-9);
+        assert!((position.quantity - 0.002998).abs() < 1e-12);
+        assert!((position.cost() - 120.043003).abs() < 1e-9);
+        assert_eq!(position.timestamp.unix_timestamp(), 1_507_725_176);
+        assert!(position.entry_order.is_some());
+    }
+
+    #[test]
+    fn peak_price_starts_at_entry() {
+        let mut position = Position::new("BTCUSDT".to_string(), 100_f64, 1_f64, UtcDateTime::now());
+
+        assert_eq!(position.peak_price(), 100_f64);
+
+        position.highest_price = 0_f64;
+
+        assert_eq!(position.peak_price(), 100_f64);
+    }
+
+    #[test]
+    fn aggregates_tick_candles_into_intervals() {
+        let mut aggregator = CandleAggregator::new(
+            Duration::from_secs(60 * 60),
+            Duration::from_secs(30 * 60)
+        );
+        let candle = |timestamp, open, high, low, close| ProcessedCandle {
+            timestamp,
+            open,
+            high,
+            low,
+            close,
+            volume: 1_f64,
+        };
+
+        assert!(aggregator.push(&candle(3600, 10_f64, 12_f64, 9_f64, 11_f64)).is_empty());
+
+        let completed = aggregator.push(&candle(5400, 11_f64, 15_f64, 8_f64, 14_f64));
+
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].timestamp, 3600);
+        assert_eq!(completed[0].open, 10_f64);
+        assert_eq!(completed[0].high, 15_f64);
+        assert_eq!(completed[0].low, 8_f64);
+        assert_eq!(completed[0].close, 14_f64);
+        assert_eq!(completed[0].volume, 2_f64);
+
+        assert!(aggregator.push(&candle(7200, 14_f64, 14_f64, 14_f64, 14_f64)).is_empty());
+
+        let completed = aggregator.push(&candle(10800, 13_f64, 13_f64, 13_f64, 13_f64));
+
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].timestamp, 7200);
+        assert_eq!(completed[0].close, 14_f64);
+    }
+
+    #[test]
+    fn pair_position_pnl_combines_both_legs() {
+        let position = PairPosition {
+            id: Uuid::new_v4(),
+            pair: "BTCUSDT/ETHUSDT".to_string(),
+            hedge_ratio: 20_f64,
+            legs: vec![
+                PositionLeg {
+                    symbol: "BTCUSDT".to_string(),
+                    side: LegSide::Long,
+                    entry_price: 40000_f64,
+                    quantity: 0.1,
+                },
+                PositionLeg {
+                    symbol: "ETHUSDT".to_string(),
+                    side: LegSide::Short,
+                    entry_price: 2000_f64,
+                    quantity: 2_f64,
+                }
+            ],
+            timestamp: UtcDateTime::now(),
+        };
+
+        let price_of = |symbol: &str| if symbol == "BTCUSDT" { 41000_f64 } else { 2100_f64 };
+
+        assert!((position.pnl_percentage(price_of) + 1.25).abs() < 1e-9);
+    }
+}
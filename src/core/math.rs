@@ -1,14 +1,34 @@
-use rust_decimal::{ dec, prelude::FromPrimitive, Decimal };
+use rust_decimal::{
+    dec,
+    prelude::FromPrimitive,
+    Decimal,
+    MathematicalOps,
+    RoundingStrategy,
+};
 
 pub struct TradingMath;
 
+/// Statistical helpers of the strategy. Variances and standard deviations are population ones,
+/// series indicators return one value per full `period` window, so their first value belongs
+/// to the `period`-th input, and percentages are expressed in percent
 pub trait Math {
     fn calculate_mean(values: &[f64]) -> Decimal {
-        let sum = values
+        let values: Vec<Decimal> = values
             .iter()
-            .fold(dec!(0), |acc, val| { acc + Decimal::from_f64(*val).unwrap_or_default() });
+            .map(|value| Decimal::from_f64(*value).unwrap_or_default())
+            .collect();
 
-        sum / Decimal::from_usize(values.len()).unwrap_or_default()
+        mean(&values)
+    }
+    fn calculate_percentage(value: f64, part: f64) -> Decimal {
+        let value = Decimal::from_f64(value).unwrap_or_default();
+        let part = Decimal::from_f64(part).unwrap_or_default();
+
+        if value.is_zero() {
+            return Decimal::ZERO;
+        }
+
+        (part / value) * dec!(100)
     }
     fn calculate_standard_deviation(values: &[Decimal]) -> Decimal;
     fn calculate_variance(values: &[Decimal]) -> Decimal;
@@ -76,6 +96,7 @@ pub trait Math {
         close: &[Decimal],
         period: usize
     ) -> Vec<Decimal>;
+    #[allow(clippy::too_many_arguments)]
     fn calculate_chandelier_exit(
         high: &[Decimal],
         low: &[Decimal],
@@ -89,7 +110,471 @@ pub trait Math {
     fn truncate_to_precision(value: Decimal, precision: u32) -> Decimal;
 }
 
-impl Math for TradingMath {}
+impl Math for TradingMath {
+    fn calculate_standard_deviation(values: &[Decimal]) -> Decimal {
+        Self::calculate_variance(values).sqrt().unwrap_or_default()
+    }
+
+    fn calculate_variance(values: &[Decimal]) -> Decimal {
+        if values.is_empty() {
+            return Decimal::ZERO;
+        }
+
+        let mean = mean(values);
+        let squared_differences = values
+            .iter()
+            .map(|value| (value - mean) * (value - mean))
+            .sum::<Decimal>();
+
+        squared_differences / Decimal::from(values.len())
+    }
+
+    fn calculate_z_score(value: Decimal, mean: Decimal, std_dev: Decimal) -> Decimal {
+        if std_dev.is_zero() {
+            return Decimal::ZERO;
+        }
+
+        (value - mean) / std_dev
+    }
+
+    /// Returns the `(upper, middle, lower)` bands
+    fn calculate_bollinger_bands(
+        prices: &[Decimal],
+        period: usize,
+        deviations: Decimal
+    ) -> (Vec<Decimal>, Vec<Decimal>, Vec<Decimal>) {
+        let mut upper = Vec::new();
+        let mut middle = Vec::new();
+        let mut lower = Vec::new();
+
+        for window in windows(prices, period) {
+            let mean = mean(window);
+            let band_width = Self::calculate_standard_deviation(window) * deviations;
+
+            upper.push(mean + band_width);
+            middle.push(mean);
+            lower.push(mean - band_width);
+        }
+
+        (upper, middle, lower)
+    }
+
+    fn calculate_simple_moving_average(values: &[Decimal], period: usize) -> Vec<Decimal> {
+        windows(values, period).map(mean).collect()
+    }
+
+    /// Seeded with the simple average of the first `period` values
+    fn calculate_exponential_moving_average(values: &[Decimal], period: usize) -> Vec<Decimal> {
+        if period == 0 || values.len() < period {
+            return Vec::new();
+        }
+
+        let smoothing = dec!(2) / Decimal::from(period + 1);
+        let mut ema = mean(&values[..period]);
+        let mut averages = vec![ema];
+
+        for value in &values[period..] {
+            ema = (value - ema) * smoothing + ema;
+            averages.push(ema);
+        }
+
+        averages
+    }
+
+    /// Wilder's RSI, the first value needs `period + 1` prices
+    fn calculate_rsi(prices: &[Decimal], period: usize) -> Vec<Decimal> {
+        if period == 0 || prices.len() <= period {
+            return Vec::new();
+        }
+
+        let changes: Vec<Decimal> = prices
+            .windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .collect();
+        let period_decimal = Decimal::from(period);
+
+        let mut average_gain = changes[..period]
+            .iter()
+            .map(|change| (*change).max(Decimal::ZERO))
+            .sum::<Decimal>() / period_decimal;
+        let mut average_loss = changes[..period]
+            .iter()
+            .map(|change| (-change).max(Decimal::ZERO))
+            .sum::<Decimal>() / period_decimal;
+        let mut rsi = vec![relative_strength_index(average_gain, average_loss)];
+
+        for change in &changes[period..] {
+            average_gain =
+                (average_gain * (period_decimal - Decimal::ONE) + (*change).max(Decimal::ZERO)) /
+                period_decimal;
+            average_loss =
+                (average_loss * (period_decimal - Decimal::ONE) + (-change).max(Decimal::ZERO)) /
+                period_decimal;
+
+            rsi.push(relative_strength_index(average_gain, average_loss));
+        }
+
+        rsi
+    }
+
+    /// Returns the `(macd, signal, histogram)` lines, the signal and histogram lines start
+    /// `signal_period - 1` values after the MACD line
+    fn calculate_macd(
+        prices: &[Decimal],
+        fast_period: usize,
+        slow_period: usize,
+        signal_period: usize
+    ) -> (Vec<Decimal>, Vec<Decimal>, Vec<Decimal>) {
+        let fast_ema = Self::calculate_exponential_moving_average(prices, fast_period);
+        let slow_ema = Self::calculate_exponential_moving_average(prices, slow_period);
+        let offset = slow_period.saturating_sub(fast_period);
+
+        let macd: Vec<Decimal> = slow_ema
+            .iter()
+            .zip(fast_ema.iter().skip(offset))
+            .map(|(slow, fast)| fast - slow)
+            .collect();
+        let signal = Self::calculate_exponential_moving_average(&macd, signal_period);
+        let histogram = macd
+            .iter()
+            .skip(signal_period.saturating_sub(1))
+            .zip(signal.iter())
+            .map(|(macd, signal)| macd - signal)
+            .collect();
+
+        (macd, signal, histogram)
+    }
+
+    /// Number of bars for a deviation to halve, `Decimal::MAX` when the series does not revert
+    fn calculate_half_life(prices: &[Decimal]) -> Decimal {
+        let (lagged, changes) = lagged_changes(prices);
+
+        match regression(&lagged, &changes) {
+            Some(fit) if fit.slope < Decimal::ZERO => -Decimal::TWO.ln() / fit.slope,
+            _ => Decimal::MAX,
+        }
+    }
+
+    /// Estimated from the growth of the lagged differences' deviation, below 0.5 the series
+    /// reverts to its mean, above 0.5 it trends
+    fn calculate_hurst_exponent(prices: &[Decimal]) -> Decimal {
+        let max_lag = (prices.len() / 2).min(HURST_MAX_LAG);
+
+        let (log_lags, log_deviations): (Vec<Decimal>, Vec<Decimal>) = (2..=max_lag)
+            .filter_map(|lag| {
+                let differences: Vec<Decimal> = prices
+                    .iter()
+                    .skip(lag)
+                    .zip(prices.iter())
+                    .map(|(current, lagged)| current - lagged)
+                    .collect();
+                let deviation = Self::calculate_standard_deviation(&differences);
+
+                Some((Decimal::from(lag).checked_ln()?, deviation.checked_ln()?))
+            })
+            .unzip();
+
+        regression(&log_lags, &log_deviations)
+            .map(|fit| fit.slope)
+            .unwrap_or(dec!(0.5))
+    }
+
+    /// Dickey-Fuller t-statistic of `Δy = α + βy(t-1)`, below about -2.86 the series is
+    /// stationary at the 5% level
+    fn calculate_adf_statistic(prices: &[Decimal]) -> Decimal {
+        let (lagged, changes) = lagged_changes(prices);
+
+        regression(&lagged, &changes)
+            .and_then(|fit| fit.slope_t_statistic())
+            .unwrap_or_default()
+    }
+
+    /// Engle-Granger test, the ADF statistic of the spread between both series
+    fn calculate_cointegration(series_a: &[Decimal], series_b: &[Decimal]) -> Decimal {
+        let hedge_ratio = Self::calculate_hedge_ratio(series_a, series_b);
+        let spread = Self::calculate_spread(series_a, series_b, hedge_ratio);
+
+        Self::calculate_adf_statistic(&spread)
+    }
+
+    /// Ordinary least squares slope of `series_a` regressed on `series_b`
+    fn calculate_hedge_ratio(series_a: &[Decimal], series_b: &[Decimal]) -> Decimal {
+        let length = series_a.len().min(series_b.len());
+
+        regression(&series_b[..length], &series_a[..length])
+            .map(|fit| fit.slope)
+            .unwrap_or_default()
+    }
+
+    fn calculate_spread(
+        series_a: &[Decimal],
+        series_b: &[Decimal],
+        hedge_ratio: Decimal
+    ) -> Vec<Decimal> {
+        series_a
+            .iter()
+            .zip(series_b.iter())
+            .map(|(a, b)| a - hedge_ratio * b)
+            .collect()
+    }
+
+    /// `1` to buy below `-entry_threshold`, `-1` to sell above `entry_threshold`, `0` otherwise
+    fn calculate_entry_signals(z_scores: &[Decimal], entry_threshold: Decimal) -> Vec<i8> {
+        z_scores
+            .iter()
+            .map(|z_score| {
+                if *z_score <= -entry_threshold {
+                    1
+                } else if *z_score >= entry_threshold {
+                    -1
+                } else {
+                    0
+                }
+            })
+            .collect()
+    }
+
+    /// `1` once the z-score is back within `exit_threshold` of the mean, `0` otherwise
+    fn calculate_exit_signals(z_scores: &[Decimal], exit_threshold: Decimal) -> Vec<i8> {
+        z_scores
+            .iter()
+            .map(|z_score| i8::from(z_score.abs() <= exit_threshold))
+            .collect()
+    }
+
+    /// Fraction of the capital to risk, never negative
+    fn calculate_kelly_criterion(win_prob: Decimal, win_loss_ratio: Decimal) -> Decimal {
+        if win_loss_ratio <= Decimal::ZERO {
+            return Decimal::ZERO;
+        }
+
+        (win_prob - (Decimal::ONE - win_prob) / win_loss_ratio).max(Decimal::ZERO)
+    }
+
+    /// Quantity risking `risk_factor` of the account on a `volatility` fraction price move,
+    /// capped by the quantity the account can afford
+    fn calculate_optimal_position_size(
+        price: Decimal,
+        volatility: Decimal,
+        account_size: Decimal,
+        risk_factor: Decimal
+    ) -> Decimal {
+        if price <= Decimal::ZERO || volatility <= Decimal::ZERO {
+            return Decimal::ZERO;
+        }
+
+        let quantity = (account_size * risk_factor) / (price * volatility);
+
+        quantity.min(account_size / price).max(Decimal::ZERO)
+    }
+
+    /// Per-period ratio, not annualized
+    fn calculate_sharpe_ratio(returns: &[Decimal], risk_free_rate: Decimal) -> Decimal {
+        let std_dev = Self::calculate_standard_deviation(returns);
+
+        if std_dev.is_zero() {
+            return Decimal::ZERO;
+        }
+
+        (mean(returns) - risk_free_rate) / std_dev
+    }
+
+    /// Per-period ratio, not annualized
+    fn calculate_sortino_ratio(returns: &[Decimal], risk_free_rate: Decimal) -> Decimal {
+        if returns.is_empty() {
+            return Decimal::ZERO;
+        }
+
+        let downside_variance = returns
+            .iter()
+            .map(|value| (value - risk_free_rate).min(Decimal::ZERO))
+            .map(|shortfall| shortfall * shortfall)
+            .sum::<Decimal>() / Decimal::from(returns.len());
+        let downside_deviation = downside_variance.sqrt().unwrap_or_default();
+
+        if downside_deviation.is_zero() {
+            return Decimal::ZERO;
+        }
+
+        (mean(returns) - risk_free_rate) / downside_deviation
+    }
+
+    /// Largest peak-to-trough decline in percent
+    fn calculate_max_drawdown(equity_curve: &[Decimal]) -> Decimal {
+        let mut peak = Decimal::MIN;
+        let mut max_drawdown = Decimal::ZERO;
+
+        for equity in equity_curve {
+            peak = peak.max(*equity);
+
+            if peak > Decimal::ZERO {
+                max_drawdown = max_drawdown.max(((peak - equity) / peak) * dec!(100));
+            }
+        }
+
+        max_drawdown
+    }
+
+    /// Wilder's average true range
+    fn calculate_atr(
+        high: &[Decimal],
+        low: &[Decimal],
+        close: &[Decimal],
+        period: usize
+    ) -> Vec<Decimal> {
+        let true_ranges = true_ranges(high, low, close);
+
+        if period == 0 || true_ranges.len() < period {
+            return Vec::new();
+        }
+
+        let period_decimal = Decimal::from(period);
+        let mut atr = mean(&true_ranges[..period]);
+        let mut averages = vec![atr];
+
+        for true_range in &true_ranges[period..] {
+            atr = (atr * (period_decimal - Decimal::ONE) + true_range) / period_decimal;
+            averages.push(atr);
+        }
+
+        averages
+    }
+
+    /// Returns the `(long, short)` exits, `multiplier` ATRs below the highest high and above
+    /// the lowest low of the last `period` bars
+    fn calculate_chandelier_exit(
+        high: &[Decimal],
+        low: &[Decimal],
+        close: &[Decimal],
+        period: usize,
+        multiplier: Decimal
+    ) -> (Vec<Decimal>, Vec<Decimal>) {
+        let atr = Self::calculate_atr(high, low, close, period);
+
+        windows(high, period)
+            .zip(windows(low, period))
+            .zip(atr)
+            .map(|((highs, lows), atr)| {
+                let highest_high = highs.iter().copied().fold(Decimal::MIN, Decimal::max);
+                let lowest_low = lows.iter().copied().fold(Decimal::MAX, Decimal::min);
+
+                (highest_high - multiplier * atr, lowest_low + multiplier * atr)
+            })
+            .unzip()
+    }
+
+    fn round_to_tick(value: Decimal, tick_size: Decimal) -> Decimal {
+        if tick_size <= Decimal::ZERO {
+            return value;
+        }
+
+        (value / tick_size).round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero) *
+            tick_size
+    }
+
+    fn truncate_to_precision(value: Decimal, precision: u32) -> Decimal {
+        value.trunc_with_scale(precision)
+    }
+}
+
+const HURST_MAX_LAG: usize = 20;
+
+struct Regression {
+    slope: Decimal,
+    residual_variance: Decimal,
+    x_sum_of_squares: Decimal,
+}
+
+impl Regression {
+    fn slope_t_statistic(&self) -> Option<Decimal> {
+        let standard_error = (self.residual_variance / self.x_sum_of_squares).sqrt()?;
+
+        (!standard_error.is_zero()).then(|| self.slope / standard_error)
+    }
+}
+
+fn mean(values: &[Decimal]) -> Decimal {
+    if values.is_empty() {
+        return Decimal::ZERO;
+    }
+
+    values.iter().sum::<Decimal>() / Decimal::from(values.len())
+}
+
+fn windows(values: &[Decimal], period: usize) -> impl Iterator<Item = &[Decimal]> {
+    values.windows(period.max(1)).filter(move |_| period > 0)
+}
+
+fn relative_strength_index(average_gain: Decimal, average_loss: Decimal) -> Decimal {
+    if average_loss.is_zero() {
+        return dec!(100);
+    }
+
+    dec!(100) - dec!(100) / (Decimal::ONE + average_gain / average_loss)
+}
+
+fn true_ranges(high: &[Decimal], low: &[Decimal], close: &[Decimal]) -> Vec<Decimal> {
+    high.iter()
+        .zip(low.iter())
+        .enumerate()
+        .map(|(index, (high, low))| {
+            let range = high - low;
+
+            match index.checked_sub(1).and_then(|previous| close.get(previous)) {
+                Some(previous_close) =>
+                    range.max((high - previous_close).abs()).max((low - previous_close).abs()),
+                None => range,
+            }
+        })
+        .collect()
+}
+
+/// Previous values and the change to the next one, the regressors of the Dickey-Fuller test
+fn lagged_changes(prices: &[Decimal]) -> (Vec<Decimal>, Vec<Decimal>) {
+    prices
+        .windows(2)
+        .map(|pair| (pair[0], pair[1] - pair[0]))
+        .unzip()
+}
+
+/// Ordinary least squares fit of `y = intercept + slope * x`
+fn regression(x: &[Decimal], y: &[Decimal]) -> Option<Regression> {
+    if x.len() < 3 || x.len() != y.len() {
+        return None;
+    }
+
+    let x_mean = mean(x);
+    let y_mean = mean(y);
+
+    let x_sum_of_squares = x
+        .iter()
+        .map(|x| (x - x_mean) * (x - x_mean))
+        .sum::<Decimal>();
+    let covariance = x
+        .iter()
+        .zip(y.iter())
+        .map(|(x, y)| (x - x_mean) * (y - y_mean))
+        .sum::<Decimal>();
+
+    if x_sum_of_squares.is_zero() {
+        return None;
+    }
+
+    let slope = covariance / x_sum_of_squares;
+    let intercept = y_mean - slope * x_mean;
+    let residual_sum_of_squares = x
+        .iter()
+        .zip(y.iter())
+        .map(|(x, y)| y - (intercept + slope * x))
+        .map(|residual| residual * residual)
+        .sum::<Decimal>();
+
+    Some(Regression {
+        slope,
+        residual_variance: residual_sum_of_squares / Decimal::from(x.len() - 2),
+        x_sum_of_squares,
+    })
+}
 
 #[cfg(test)]
 mod tests {
@@ -97,6 +582,38 @@ mod tests {
 
     use super::{ TradingMath, Math };
 
+    fn assert_close(actual: Decimal, expected: Decimal) {
+        assert!(
+            (actual - expected).abs() < dec!(0.0001),
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    fn assert_all_close(actual: &[Decimal], expected: &[Decimal]) {
+        assert_eq!(actual.len(), expected.len(), "expected {expected:?}, got {actual:?}");
+
+        actual
+            .iter()
+            .zip(expected.iter())
+            .for_each(|(actual, expected)| assert_close(*actual, *expected));
+    }
+
+    /// Deterministic random walk driven by a linear congruential generator
+    fn random_walk(length: usize) -> Vec<Decimal> {
+        let mut seed: u64 = 42;
+        let mut price = dec!(100);
+
+        (0..length)
+            .map(|_| {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                let step = Decimal::from((seed >> 33) % 2001) / dec!(1000) - Decimal::ONE;
+
+                price += step;
+                price
+            })
+            .collect()
+    }
+
     #[test]
     fn integer_percentage_calculation() {
         let integer_vector: Vec<(f64, f64, Decimal)> = vec![
@@ -118,4 +635,205 @@ mod tests {
             assert_eq!(percentage, expected);
         });
     }
+
+    #[test]
+    fn mean_calculation() {
+        assert_eq!(TradingMath::calculate_mean(&[2.0, 4.0, 9.0]), dec!(5));
+        assert_eq!(TradingMath::calculate_mean(&[]), Decimal::ZERO);
+    }
+
+    #[test]
+    fn variance_and_standard_deviation() {
+        let values = [2, 4, 4, 4, 5, 5, 7, 9].map(Decimal::from);
+
+        assert_eq!(TradingMath::calculate_variance(&values), dec!(4));
+        assert_eq!(TradingMath::calculate_standard_deviation(&values), dec!(2));
+        assert_eq!(TradingMath::calculate_variance(&[]), Decimal::ZERO);
+    }
+
+    #[test]
+    fn z_score() {
+        assert_eq!(TradingMath::calculate_z_score(dec!(12), dec!(10), dec!(2)), dec!(1));
+        assert_eq!(TradingMath::calculate_z_score(dec!(7), dec!(10), dec!(2)), dec!(-1.5));
+        assert_eq!(TradingMath::calculate_z_score(dec!(7), dec!(10), dec!(0)), dec!(0));
+    }
+
+    #[test]
+    fn bollinger_bands() {
+        let prices = [2, 4, 4, 4, 5, 5, 7, 9].map(Decimal::from);
+
+        let (upper, middle, lower) = TradingMath::calculate_bollinger_bands(&prices, 8, dec!(2));
+
+        assert_eq!(upper, vec![dec!(9)]);
+        assert_eq!(middle, vec![dec!(5)]);
+        assert_eq!(lower, vec![dec!(1)]);
+    }
+
+    #[test]
+    fn moving_averages() {
+        let values = [2, 4, 6, 8, 12].map(Decimal::from);
+
+        assert_eq!(
+            TradingMath::calculate_simple_moving_average(&values, 3),
+            vec![dec!(4), dec!(6), dec!(26) / dec!(3)]
+        );
+        assert_eq!(
+            TradingMath::calculate_exponential_moving_average(&values, 3),
+            vec![dec!(4), dec!(6), dec!(9)]
+        );
+        assert!(TradingMath::calculate_simple_moving_average(&values, 6).is_empty());
+        assert!(TradingMath::calculate_exponential_moving_average(&values, 0).is_empty());
+    }
+
+    #[test]
+    fn rsi() {
+        let prices = [1, 2, 3, 2, 3].map(Decimal::from);
+
+        assert_eq!(
+            TradingMath::calculate_rsi(&prices, 2),
+            vec![dec!(100), dec!(50), dec!(75)]
+        );
+        assert!(TradingMath::calculate_rsi(&prices, 5).is_empty());
+    }
+
+    #[test]
+    fn macd() {
+        let prices = [2, 4, 6, 8, 12].map(Decimal::from);
+
+        let (macd, signal, histogram) = TradingMath::calculate_macd(&prices, 2, 3, 2);
+
+        assert_all_close(&macd, &[dec!(1), dec!(1), dec!(1.3333)]);
+        assert_all_close(&signal, &[dec!(1), dec!(1.2222)]);
+        assert_all_close(&histogram, &[dec!(0), dec!(0.1111)]);
+    }
+
+    #[test]
+    fn half_life_and_adf_statistic() {
+        let prices = [1, 3, 2, 4, 3].map(Decimal::from);
+
+        assert_close(TradingMath::calculate_half_life(&prices), dec!(0.5776));
+        assert_close(TradingMath::calculate_adf_statistic(&prices), dec!(-2.8284));
+
+        let geometric_decay = [64, 32, 16, 8, 4, 2, 1].map(Decimal::from);
+
+        assert_close(TradingMath::calculate_half_life(&geometric_decay), dec!(1.3863));
+
+        let trend = [1, 2, 4, 8, 16].map(Decimal::from);
+
+        assert_eq!(TradingMath::calculate_half_life(&trend), Decimal::MAX);
+    }
+
+    #[test]
+    fn hurst_exponent() {
+        let oscillating: Vec<Decimal> = (0..100)
+            .map(|index| if index % 2 == 0 { dec!(100) } else { dec!(101) })
+            .collect();
+        let walk_hurst = TradingMath::calculate_hurst_exponent(&random_walk(500));
+
+        assert!(TradingMath::calculate_hurst_exponent(&oscillating) < dec!(0.1));
+        assert!(walk_hurst > dec!(0.35) && walk_hurst < dec!(0.65), "got {walk_hurst}");
+    }
+
+    #[test]
+    fn pairs_statistics() {
+        let series_b = random_walk(200);
+        let series_a: Vec<Decimal> = series_b
+            .iter()
+            .enumerate()
+            .map(|(index, price)| {
+                price * dec!(2) + if index % 2 == 0 { dec!(0.5) } else { dec!(-0.5) }
+            })
+            .collect();
+
+        let hedge_ratio = TradingMath::calculate_hedge_ratio(&series_a, &series_b);
+
+        assert_close(hedge_ratio.round_dp(2), dec!(2));
+        assert_eq!(
+            TradingMath::calculate_spread(&[dec!(5), dec!(7)], &[dec!(2), dec!(3)], dec!(2)),
+            vec![dec!(1), dec!(1)]
+        );
+        assert!(TradingMath::calculate_cointegration(&series_a, &series_b) < dec!(-2.86));
+    }
+
+    #[test]
+    fn entry_and_exit_signals() {
+        let z_scores = [dec!(-2.5), dec!(0.3), dec!(2.1), dec!(-0.4), dec!(1)];
+
+        assert_eq!(TradingMath::calculate_entry_signals(&z_scores, dec!(2)), vec![1, 0, -1, 0, 0]);
+        assert_eq!(TradingMath::calculate_exit_signals(&z_scores, dec!(0.5)), vec![0, 1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn position_sizing() {
+        assert_eq!(TradingMath::calculate_kelly_criterion(dec!(0.6), dec!(1)), dec!(0.2));
+        assert_eq!(TradingMath::calculate_kelly_criterion(dec!(0.5), dec!(2)), dec!(0.25));
+        assert_eq!(TradingMath::calculate_kelly_criterion(dec!(0.3), dec!(1)), dec!(0));
+
+        assert_eq!(
+            TradingMath::calculate_optimal_position_size(
+                dec!(100),
+                dec!(0.02),
+                dec!(10000),
+                dec!(0.01)
+            ),
+            dec!(50)
+        );
+        assert_eq!(
+            TradingMath::calculate_optimal_position_size(
+                dec!(100),
+                dec!(0.001),
+                dec!(10000),
+                dec!(0.01)
+            ),
+            dec!(100)
+        );
+    }
+
+    #[test]
+    fn performance_metrics() {
+        let returns = [dec!(0.01), dec!(0.02), dec!(0.03)];
+
+        assert_close(TradingMath::calculate_sharpe_ratio(&returns, dec!(0)), dec!(2.4495));
+        assert_close(
+            TradingMath::calculate_sortino_ratio(
+                &[dec!(0.02), dec!(-0.01), dec!(0.03), dec!(-0.02)],
+                dec!(0)
+            ),
+            dec!(0.4472)
+        );
+        assert_eq!(
+            TradingMath::calculate_max_drawdown(&[100, 120, 90, 130, 104].map(Decimal::from)),
+            dec!(25)
+        );
+    }
+
+    #[test]
+    fn atr_and_chandelier_exit() {
+        let high = [10, 12, 11, 15].map(Decimal::from);
+        let low = [8, 9, 9, 11].map(Decimal::from);
+        let close = [9, 11, 10, 14].map(Decimal::from);
+
+        assert_eq!(
+            TradingMath::calculate_atr(&high, &low, &close, 2),
+            vec![dec!(2.5), dec!(2.25), dec!(3.625)]
+        );
+
+        let (long_exit, short_exit) = TradingMath::calculate_chandelier_exit(
+            &high,
+            &low,
+            &close,
+            2,
+            dec!(1)
+        );
+
+        assert_eq!(long_exit, vec![dec!(9.5), dec!(9.75), dec!(11.375)]);
+        assert_eq!(short_exit, vec![dec!(10.5), dec!(11.25), dec!(12.625)]);
+    }
+
+    #[test]
+    fn precision_helpers() {
+        assert_eq!(TradingMath::round_to_tick(dec!(101.237), dec!(0.05)), dec!(101.25));
+        assert_eq!(TradingMath::round_to_tick(dec!(101.237), dec!(0)), dec!(101.237));
+        assert_eq!(TradingMath::truncate_to_precision(dec!(1.23456), 2), dec!(1.23));
+    }
 }
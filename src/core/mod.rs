@@ -1,5 +1,10 @@
 pub mod bot;
 pub mod market;
+pub mod math;
+pub mod position_store;
 
+mod circuit_breaker;
+mod pair_tracker;
 mod position_manager;
+mod tracker;
 mod trading_strategy;
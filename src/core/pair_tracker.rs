@@ -0,0 +1,229 @@
+use std::{ collections::HashMap, time::Duration };
+
+use rust_decimal::{ prelude::{ FromPrimitive, ToPrimitive }, Decimal };
+use tracing::{ debug, info, warn };
+
+use crate::strategy::strategy::PairStrategy;
+use super::{ market::{ CandleAggregator, ProcessedCandle }, math::{ Math, TradingMath } };
+
+const MIN_PAIR_CANDLES: usize = 10;
+
+/// Spread statistics of a pair after its latest candles
+#[derive(Debug, Clone, Copy)]
+pub struct PairSignal {
+    pub first_price: f64,
+    pub second_price: f64,
+    /// Units of the second symbol held against one unit of the first one
+    pub hedge_ratio: f64,
+    pub z_score: f64,
+}
+
+impl PairSignal {
+    pub fn price_of(&self, pair: &PairStrategy, symbol: &str) -> f64 {
+        if symbol == pair.first_symbol { self.first_price } else { self.second_price }
+    }
+}
+
+/// Close history of both symbols of a pair over the rolling `lookback` window of `interval`
+/// candles aggregated from the `tick` candles the pair is traded on
+pub struct PairTracker {
+    pub pair: PairStrategy,
+    first_closes: Vec<f64>,
+    second_closes: Vec<f64>,
+    first_aggregator: CandleAggregator,
+    second_aggregator: CandleAggregator,
+    interval: Duration,
+    tick: Duration,
+    last_candle_timestamp: Option<u64>,
+}
+
+impl PairTracker {
+    pub fn new(pair: PairStrategy, interval: Duration, tick: Duration) -> Self {
+        Self {
+            pair,
+            first_closes: Vec::new(),
+            second_closes: Vec::new(),
+            first_aggregator: CandleAggregator::new(interval, tick),
+            second_aggregator: CandleAggregator::new(interval, tick),
+            interval,
+            tick,
+            last_candle_timestamp: None,
+        }
+    }
+
+    /// Seeds the history with the `interval` candles both symbols have a close for
+    pub fn seed(&mut self, first_candles: &[ProcessedCandle], second_candles: &[ProcessedCandle]) {
+        let second_closes: HashMap<u64, f64> = second_candles
+            .iter()
+            .map(|candle| (candle.timestamp, candle.close))
+            .collect();
+
+        for candle in first_candles {
+            if let Some(second_close) = second_closes.get(&candle.timestamp) {
+                self.push(candle.close, *second_close);
+
+                // The last tick of the seeded candle counts as processed
+                self.last_candle_timestamp = Some(
+                    candle.timestamp + self.interval.as_secs().saturating_sub(self.tick.as_secs())
+                );
+            }
+        }
+
+        info!("Initialized pair {} with {} candles", self.pair.name(), self.first_closes.len());
+    }
+
+    /// Adds the latest `tick` candles of both symbols, returning the spread signal of their
+    /// closes against the `interval` history once enough candles were collected. Candles of
+    /// different times and candles already added are ignored
+    pub fn update(
+        &mut self,
+        first: &ProcessedCandle,
+        second: &ProcessedCandle
+    ) -> Option<PairSignal> {
+        if first.timestamp != second.timestamp {
+            warn!(
+                "Skipping unaligned {} candles opened at {} and {}",
+                self.pair.name(),
+                first.timestamp,
+                second.timestamp
+            );
+            return None;
+        }
+
+        if self.last_candle_timestamp.is_some_and(|last| first.timestamp <= last) {
+            debug!("Skipping already processed {} candles: {:?}", self.pair.name(), first);
+            return None;
+        }
+
+        self.last_candle_timestamp = Some(first.timestamp);
+
+        let first_completed = self.first_aggregator.push(first);
+        let second_completed = self.second_aggregator.push(second);
+
+        for (first_candle, second_candle) in first_completed.iter().zip(&second_completed) {
+            self.push(first_candle.close, second_candle.close);
+        }
+
+        if self.first_closes.len() < MIN_PAIR_CANDLES {
+            info!("Not enough candles to trade pair {} yet", self.pair.name());
+            return None;
+        }
+
+        let first_closes = to_decimals(&self.first_closes);
+        let second_closes = to_decimals(&self.second_closes);
+
+        let hedge_ratio = TradingMath::calculate_hedge_ratio(&first_closes, &second_closes);
+        let spread = TradingMath::calculate_spread(&first_closes, &second_closes, hedge_ratio);
+        let spread_mean = spread.iter().sum::<Decimal>() / Decimal::from(spread.len());
+        let current_spread =
+            Decimal::from_f64(first.close)? - hedge_ratio * Decimal::from_f64(second.close)?;
+
+        let z_score = TradingMath::calculate_z_score(
+            current_spread,
+            spread_mean,
+            TradingMath::calculate_standard_deviation(&spread)
+        );
+
+        Some(PairSignal {
+            first_price: first.close,
+            second_price: second.close,
+            hedge_ratio: hedge_ratio.to_f64()?,
+            z_score: z_score.to_f64()?,
+        })
+    }
+
+    fn push(&mut self, first_close: f64, second_close: f64) {
+        self.first_closes.push(first_close);
+        self.second_closes.push(second_close);
+
+        if self.first_closes.len() > self.pair.lookback {
+            self.first_closes.remove(0);
+            self.second_closes.remove(0);
+        }
+    }
+}
+
+fn to_decimals(values: &[f64]) -> Vec<Decimal> {
+    values
+        .iter()
+        .filter_map(|value| Decimal::from_f64(*value))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair() -> PairStrategy {
+        PairStrategy {
+            first_symbol: "BTCUSDT".to_string(),
+            second_symbol: "ETHUSDT".to_string(),
+            lookback: 20,
+            entry_z_score: 2.0,
+            exit_z_score: 0.5,
+            capital_per_trade: 0.1,
+        }
+    }
+
+    fn candle(timestamp: u64, close: f64) -> ProcessedCandle {
+        ProcessedCandle { timestamp, open: close, high: close, low: close, close, volume: 1_f64 }
+    }
+
+    #[test]
+    fn scores_the_spread_of_aligned_candles() {
+        let second_interval = Duration::from_secs(1);
+        let mut tracker = PairTracker::new(pair(), second_interval, second_interval);
+
+        let second: Vec<ProcessedCandle> = (0..30)
+            .map(|index| candle(index, 100_f64 + (index as f64) * 2_f64))
+            .collect();
+        let first: Vec<ProcessedCandle> = second
+            .iter()
+            .enumerate()
+            .map(|(index, candle)| {
+                let noise = if index % 2 == 0 { 1_f64 } else { -1_f64 };
+                ProcessedCandle { close: candle.close * 2_f64 + noise, ..candle.clone() }
+            })
+            .skip(1)
+            .collect();
+
+        tracker.seed(&first, &second);
+
+        assert_eq!(tracker.first_closes.len(), 20);
+
+        let signal = tracker.update(&candle(30, 310_f64), &candle(30, 160_f64)).unwrap();
+
+        assert!((signal.hedge_ratio - 2_f64).abs() < 0.1);
+        assert!(signal.z_score < -2_f64);
+        assert_eq!(signal.price_of(&tracker.pair, "ETHUSDT"), 160_f64);
+    }
+
+    #[test]
+    fn aggregates_aligned_tick_candles_once() {
+        let interval = Duration::from_secs(4);
+        let mut tracker = PairTracker::new(pair(), interval, Duration::from_secs(1));
+        let history: Vec<ProcessedCandle> = (0..10)
+            .map(|index| candle(index * 4, 100_f64 + (index as f64)))
+            .collect();
+
+        tracker.seed(&history, &history);
+
+        // The last tick of the seeded candle opened at 36 was processed
+        assert!(tracker.update(&candle(39, 110_f64), &candle(39, 110_f64)).is_none());
+
+        for timestamp in 40..43 {
+            tracker.update(&candle(timestamp, 110_f64), &candle(timestamp, 110_f64)).unwrap();
+        }
+
+        assert_eq!(tracker.first_closes.len(), 10);
+
+        assert!(tracker.update(&candle(42, 110_f64), &candle(42, 110_f64)).is_none());
+        assert!(tracker.update(&candle(43, 111_f64), &candle(44, 111_f64)).is_none());
+        assert_eq!(tracker.first_closes.len(), 10);
+
+        tracker.update(&candle(43, 111_f64), &candle(43, 111_f64)).unwrap();
+
+        assert_eq!(tracker.first_closes.len(), 11);
+        assert_eq!(tracker.first_closes.last(), Some(&111_f64));
+    }
+}
@@ -1,32 +1,94 @@
-use std::collections::HashMap;
+use std::collections::{ HashMap, VecDeque };
 
 use uuid::Uuid;
-use tracing::{ info, debug };
+use tracing::{ info, debug, error, warn };
 
 use crate::api::{ client::ApiClient, error::ApiError };
-use super::market::Position;
+use super::{
+    market::{ ClosedTrade, LegSide, OrderExecution, PairPosition, Position, PositionLeg },
+    position_store::PositionStore,
+    trading_strategy::{ CycleContext, PairCycleContext },
+};
+
+/// Number of closed trades kept in memory and in the store
+const CLOSED_TRADES_HISTORY: usize = 500;
 
 pub struct PositionManager {
     open_positions: HashMap<Uuid, Position>,
+    pair_positions: HashMap<Uuid, PairPosition>,
+    closed_trades: VecDeque<ClosedTrade>,
     max_positions: usize,
+    store: Option<PositionStore>,
+}
+
+/// Order opening one leg of a pair position
+pub struct LegOrder {
+    pub symbol: String,
+    pub side: LegSide,
+    pub quantity: f64,
+    pub price: f64,
 }
 
 impl PositionManager {
     pub fn new(max_positions: usize) -> Self {
         Self {
             open_positions: HashMap::with_capacity(max_positions),
+            pair_positions: HashMap::new(),
+            closed_trades: VecDeque::new(),
             max_positions,
+            store: None,
+        }
+    }
+
+    pub fn with_store(mut self, store: PositionStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Loads the positions left open by a previous run from the store
+    pub fn restore_positions(&mut self) -> Result<usize, ApiError> {
+        let Some(store) = &self.store else {
+            return Ok(0);
+        };
+
+        let positions = store.load()?;
+        let pair_positions = store.load_pairs()?;
+
+        self.closed_trades = store.load_trades()?.into();
+        self.closed_trades.truncate(CLOSED_TRADES_HISTORY);
+
+        if positions.len() + pair_positions.len() > self.max_positions {
+            warn!(
+                "Restored {} positions, more than the configured maximum of {}",
+                positions.len() + pair_positions.len(),
+                self.max_positions
+            );
+        }
+
+        for position in positions {
+            info!("Restored position: {:?}", position);
+
+            self.open_positions.insert(position.id, position);
         }
+
+        for pair_position in pair_positions {
+            info!("Restored pair position: {:?}", pair_position);
+
+            self.pair_positions.insert(pair_position.id, pair_position);
+        }
+
+        Ok(self.len())
     }
 
+    /// Buys `quantity` of the symbol, placing the stop of the position `stop_distance` below
+    /// its entry price when given
     pub async fn open_position(
         &mut self,
-        symbol: &str,
         quantity: f64,
-        price: f64,
-        client: &dyn ApiClient
+        stop_distance: Option<f64>,
+        context: &CycleContext<'_>
     ) -> Result<f64, ApiError> {
-        if self.open_positions.len() >= self.max_positions {
+        if self.len() >= self.max_positions {
             debug!("Failed to open new position, reached maximum value");
 
             return Err(
@@ -34,12 +96,25 @@ impl PositionManager {
             );
         }
 
-        let position = client.place_order_to_buy(symbol, quantity, price).await?;
-        let position_price = position.entry_price * position.quantity;
+        let strategy = context.strategy;
+        let execution = context.api_client.place_order_to_buy(
+            &strategy.symbol,
+            quantity,
+            context.current_price
+        ).await?;
+        let mut position = Position::from_execution(
+            strategy.symbol.clone(),
+            execution,
+            strategy.base_asset(),
+            &strategy.trading_symbol
+        );
+        position.stop_price = stop_distance.map(|distance| position.entry_price - distance);
+        let position_price = position.entry_cost;
 
         info!("Opened position: {:?}", position);
 
         self.open_positions.insert(position.id, position);
+        self.persist_positions();
 
         Ok(position_price)
     }
@@ -47,32 +122,462 @@ impl PositionManager {
     pub async fn close_position(
         &mut self,
         position_id: Uuid,
-        current_price: f64,
-        client: &dyn ApiClient
+        context: &CycleContext<'_>
     ) -> Result<f64, ApiError> {
         let position = self.open_positions
             .get(&position_id)
             .ok_or(ApiError::NotFound(format!("Position with id {position_id} not found")))?;
-        let sell_price = current_price * position.quantity;
 
-        client.place_order_to_sell(&position.symbol, position.quantity).await?;
+        let execution = context.api_client.place_order_to_sell(
+            &position.symbol,
+            position.quantity,
+            context.current_price
+        ).await?;
+        let sell_price =
+            execution.quote_quantity - execution.commission_in(&context.strategy.trading_symbol);
 
-        info!("Closing position {}", position.id);
+        info!("Closing position {} with execution: {:?}", position.id, execution);
 
+        self.record_trade(ClosedTrade {
+            position_id,
+            symbol: position.symbol.clone(),
+            cost: position.cost(),
+            proceeds: sell_price,
+            timestamp: execution.timestamp,
+        });
         self.open_positions.remove(&position_id);
+        self.persist_positions();
 
         Ok(sell_price)
     }
 
-    pub fn get_positions(&self) -> impl Iterator<Item = &Position> {
-        self.open_positions.values()
+    /// Opens both legs of a pair as one position, selling the short legs first so a missing
+    /// holding fails before anything is bought. Executed legs are unwound when a leg fails,
+    /// returning the quote asset the fills and the unwinding cost without opening a position
+    pub async fn open_pair_position(
+        &mut self,
+        orders: Vec<LegOrder>,
+        context: &PairCycleContext<'_>
+    ) -> Result<f64, ApiError> {
+        if self.len() >= self.max_positions {
+            return Err(
+                ApiError::ValidationError("Maximum value of open positions reached".to_string())
+            );
+        }
+
+        let held_symbol = orders
+            .iter()
+            .filter(|order| order.side == LegSide::Short)
+            .find(|order| self.symbol_positions_count(&order.symbol) > 0);
+
+        if let Some(order) = held_symbol {
+            return Err(
+                ApiError::ValidationError(
+                    format!("Short leg would sell the {} holdings of open positions", order.symbol)
+                )
+            );
+        }
+
+        let strategy = context.strategy;
+        let mut orders = orders;
+        orders.sort_by_key(|order| order.side == LegSide::Long);
+
+        let mut legs = Vec::with_capacity(orders.len());
+        let mut balance_difference = 0_f64;
+
+        for order in &orders {
+            let is_buy = order.side == LegSide::Long;
+
+            match send_leg_order(context.api_client, order, is_buy).await {
+                Ok(execution) => {
+                    balance_difference += quote_flow(&execution, is_buy, &strategy.trading_symbol);
+                    legs.push(
+                        PositionLeg::from_execution(
+                            order.symbol.clone(),
+                            order.side,
+                            &execution,
+                            strategy.base_asset_of(&order.symbol)
+                        )
+                    );
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to open {} leg of pair {}: {}",
+                        order.symbol,
+                        context.pair.name(),
+                        e
+                    );
+
+                    balance_difference += self.unwind_legs(&legs, context).await;
+
+                    return Ok(balance_difference);
+                }
+            }
+        }
+
+        let pair_position = PairPosition {
+            id: Uuid::new_v4(),
+            pair: context.pair.name(),
+            hedge_ratio: context.signal.hedge_ratio,
+            legs,
+            timestamp: time::UtcDateTime::now(),
+        };
+
+        info!("Opened pair position: {:?}", pair_position);
+
+        self.pair_positions.insert(pair_position.id, pair_position);
+        self.persist_positions();
+
+        Ok(balance_difference)
+    }
+
+    /// Closes the open legs of a pair position, legs failing to close stay open
+    /// to be retried on the next cycle
+    pub async fn close_pair_position(
+        &mut self,
+        position_id: Uuid,
+        context: &PairCycleContext<'_>
+    ) -> Result<f64, ApiError> {
+        let position = self.pair_positions
+            .get(&position_id)
+            .ok_or(ApiError::NotFound(format!("Pair position with id {position_id} not found")))?;
+
+        let mut remaining_legs = Vec::new();
+        let mut balance_difference = 0_f64;
+
+        for leg in position.legs.clone() {
+            let order = LegOrder {
+                price: context.signal.price_of(context.pair, &leg.symbol),
+                symbol: leg.symbol.clone(),
+                side: leg.side,
+                quantity: leg.quantity,
+            };
+            let is_buy = leg.side == LegSide::Short;
+
+            match send_leg_order(context.api_client, &order, is_buy).await {
+                Ok(execution) => {
+                    balance_difference +=
+                        quote_flow(&execution, is_buy, &context.strategy.trading_symbol);
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to close {} leg of pair position {}: {}",
+                        leg.symbol,
+                        position_id,
+                        e
+                    );
+                    remaining_legs.push(leg);
+                }
+            }
+        }
+
+        if remaining_legs.is_empty() {
+            info!("Closed pair position {}", position_id);
+            self.pair_positions.remove(&position_id);
+        } else if let Some(position) = self.pair_positions.get_mut(&position_id) {
+            position.legs = remaining_legs;
+        }
+
+        self.persist_positions();
+
+        Ok(balance_difference)
+    }
+
+    /// Reverts legs of a pair position that could not be opened completely, returning the
+    /// quote asset received or spent by the reverting orders
+    async fn unwind_legs(&self, legs: &[PositionLeg], context: &PairCycleContext<'_>) -> f64 {
+        let mut balance_difference = 0_f64;
+
+        for leg in legs {
+            let order = LegOrder {
+                price: context.signal.price_of(context.pair, &leg.symbol),
+                symbol: leg.symbol.clone(),
+                side: leg.side,
+                quantity: leg.quantity,
+            };
+
+            let is_buy = leg.side == LegSide::Short;
+
+            match send_leg_order(context.api_client, &order, is_buy).await {
+                Ok(execution) => {
+                    balance_difference +=
+                        quote_flow(&execution, is_buy, &context.strategy.trading_symbol);
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to unwind {} leg, it has to be closed manually: {}",
+                        leg.symbol,
+                        e
+                    );
+                }
+            }
+        }
+
+        balance_difference
+    }
+
+    /// Adds a closed trade to the front of the history, dropping the oldest trades
+    fn record_trade(&mut self, trade: ClosedTrade) {
+        self.closed_trades.push_front(trade);
+        self.closed_trades.truncate(CLOSED_TRADES_HISTORY);
+
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save_trades(self.closed_trades.iter()) {
+                error!("Failed to persist closed trades: {}", e);
+            }
+        }
+    }
+
+    fn persist_positions(&self) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save(self.open_positions.values()) {
+                error!("Failed to persist open positions: {}", e);
+            }
+
+            if let Err(e) = store.save_pairs(self.pair_positions.values()) {
+                error!("Failed to persist open pair positions: {}", e);
+            }
+        }
+    }
+
+    pub fn get_symbol_positions<'a>(
+        &'a self,
+        symbol: &'a str
+    ) -> impl Iterator<Item = &'a Position> {
+        self.open_positions.values().filter(move |position| position.symbol == symbol)
+    }
+
+    pub fn symbol_positions_count(&self, symbol: &str) -> usize {
+        self.get_symbol_positions(symbol).count()
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.open_positions.is_empty()
+    /// Raises the recorded peak of the open positions of `symbol` reaching a new high
+    pub fn update_peak_prices(&mut self, symbol: &str, price: f64) {
+        let mut is_updated = false;
+
+        for position in self.open_positions.values_mut() {
+            if position.symbol == symbol && price > position.peak_price() {
+                position.highest_price = price;
+                is_updated = true;
+            }
+        }
+
+        if is_updated {
+            self.persist_positions();
+        }
     }
 
+    /// The latest `count` closed trades, newest first
+    pub fn recent_trades(&self, count: usize) -> impl Iterator<Item = &ClosedTrade> {
+        self.closed_trades.iter().take(count)
+    }
+
+    /// Market value of the open positions, a short leg counting as the liability of buying
+    /// it back and positions without a known price being valued at their entry price
+    pub fn open_value(&self, price_of: impl Fn(&str) -> Option<f64>) -> f64 {
+        let positions_value: f64 = self.open_positions
+            .values()
+            .map(|position| {
+                price_of(&position.symbol).unwrap_or(position.entry_price) * position.quantity
+            })
+            .sum();
+
+        let legs_value: f64 = self.pair_positions
+            .values()
+            .flat_map(|position| position.legs.iter())
+            .map(|leg| {
+                let value = price_of(&leg.symbol).unwrap_or(leg.entry_price) * leg.quantity;
+
+                match leg.side {
+                    LegSide::Long => value,
+                    LegSide::Short => -value,
+                }
+            })
+            .sum();
+
+        positions_value + legs_value
+    }
+
+    pub fn get_pair_position(&self, pair: &str) -> Option<&PairPosition> {
+        self.pair_positions.values().find(|position| position.pair == pair)
+    }
+
+    /// Number of open positions, a pair position counting as one
     pub fn len(&self) -> usize {
-        self.open_positions.len()
+        self.open_positions.len() + self.pair_positions.len()
+    }
+}
+
+async fn send_leg_order(
+    api_client: &dyn ApiClient,
+    order: &LegOrder,
+    is_buy: bool
+) -> Result<OrderExecution, ApiError> {
+    if is_buy {
+        api_client.place_order_to_buy(&order.symbol, order.quantity, order.price).await
+    } else {
+        api_client.place_order_to_sell(&order.symbol, order.quantity, order.price).await
+    }
+}
+
+/// Quote asset received by a sell or spent by a buy, including the commission paid in quote
+fn quote_flow(execution: &OrderExecution, is_buy: bool, quote_asset: &str) -> f64 {
+    let commission = execution.commission_in(quote_asset);
+
+    if is_buy {
+        -(execution.quote_quantity + commission)
+    } else {
+        execution.quote_quantity - commission
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::UtcDateTime;
+
+    use super::*;
+    use crate::{
+        core::{ pair_tracker::PairSignal, tracker::Indicators },
+        strategy::strategy::{ PairStrategy, Strategy },
+        tests::{
+            mock_binance::{ MockBinance, ORDER_FIXTURE, ORDER_PATH },
+            strategy::strategy,
+        },
+    };
+
+    fn pair() -> PairStrategy {
+        PairStrategy {
+            first_symbol: "BTCUSDT".to_string(),
+            second_symbol: "ETHUSDT".to_string(),
+            lookback: 20,
+            entry_z_score: 2_f32,
+            exit_z_score: 0_f32,
+            capital_per_trade: 0.5,
+        }
+    }
+
+    fn orders() -> Vec<LegOrder> {
+        vec![
+            LegOrder {
+                symbol: "ETHUSDT".to_string(),
+                side: LegSide::Long,
+                quantity: 1_f64,
+                price: 90_f64,
+            },
+            LegOrder {
+                symbol: "BTCUSDT".to_string(),
+                side: LegSide::Short,
+                quantity: 1_f64,
+                price: 90_f64,
+            }
+        ]
+    }
+
+    fn pair_context<'a>(
+        pair: &'a PairStrategy,
+        strategy: &'a Strategy,
+        api_client: &'a dyn ApiClient
+    ) -> PairCycleContext<'a> {
+        PairCycleContext {
+            pair,
+            signal: PairSignal {
+                first_price: 90_f64,
+                second_price: 90_f64,
+                hedge_ratio: 1_f64,
+                z_score: 2.5,
+            },
+            allow_entries: true,
+            account_balance: 1000_f64,
+            max_open_positions: 5,
+            strategy,
+            api_client,
+        }
+    }
+
+    #[tokio::test]
+    async fn unwinding_a_failed_pair_returns_the_fill_costs() {
+        let mock = MockBinance::start().await;
+        // The exchange info fixture only knows BTCUSDT, so the ETHUSDT leg fails to open
+        mock.respond(ORDER_PATH, &ORDER_FIXTURE.replace("BNB", "USDT"));
+        let client = mock.client();
+        let (pair, strategy) = (pair(), strategy(""));
+        let mut position_manager = PositionManager::new(5);
+
+        let balance_difference = position_manager
+            .open_pair_position(orders(), &pair_context(&pair, &strategy, &client)).await
+            .unwrap();
+
+        let sides: Vec<String> = mock.requests(ORDER_PATH)
+            .iter()
+            .filter_map(|request| {
+                request.query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("side="))
+                    .map(str::to_string)
+            })
+            .collect();
+
+        assert_eq!(sides, ["SELL", "BUY"]);
+        // Both the short sale and the buy back paid a 0.0001 commission
+        assert!((balance_difference + 0.0002).abs() < 1e-9);
+        assert_eq!(position_manager.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn short_leg_does_not_sell_holdings_of_open_positions() {
+        let mock = MockBinance::start().await;
+        let client = mock.client();
+        let (pair, strategy) = (pair(), strategy(""));
+        let mut position_manager = PositionManager::new(5);
+        let position = Position::new(
+            "BTCUSDT".to_string(),
+            90_f64,
+            1_f64,
+            UtcDateTime::UNIX_EPOCH
+        );
+        position_manager.open_positions.insert(position.id, position);
+
+        let result = position_manager
+            .open_pair_position(orders(), &pair_context(&pair, &strategy, &client)).await;
+
+        assert!(matches!(result, Err(ApiError::ValidationError(_))));
+        assert!(mock.requests(ORDER_PATH).is_empty());
+        assert_eq!(position_manager.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn closed_trades_cost_the_quote_spent_on_entry() {
+        let mock = MockBinance::start().await;
+        mock.respond(ORDER_PATH, &ORDER_FIXTURE.replace("BNB", "USDT").replace("0.0001", "0.1"));
+        let client = mock.client();
+        let strategy = strategy("");
+        let context = CycleContext {
+            current_price: 90_f64,
+            candle_timestamp: 0,
+            indicators: Indicators {
+                deviation: 0_f64,
+                mean: 100_f64,
+                z_score: 0_f64,
+                bollinger_bands: None,
+                rsi: None,
+                regime: None,
+                atr: None,
+                chandelier_exit: None,
+            },
+            account_balance: 1000_f64,
+            max_open_positions: 5,
+            strategy: &strategy,
+            api_client: &client,
+        };
+        let mut position_manager = PositionManager::new(5);
+
+        let position_price = position_manager.open_position(1_f64, None, &context).await.unwrap();
+        let position_id = *position_manager.open_positions.keys().next().unwrap();
+        position_manager.close_position(position_id, &context).await.unwrap();
+
+        // The 0.1 commission paid in quote on entry is part of the cost
+        let trade = position_manager.recent_trades(1).next().unwrap();
+        assert!((position_price - 90.1).abs() < 1e-9);
+        assert_eq!(trade.cost, position_price);
     }
 }
@@ -0,0 +1,180 @@
+use std::{ fs, io::ErrorKind, path::{ Path, PathBuf } };
+
+use serde::{ de::DeserializeOwned, Serialize };
+use tracing::debug;
+
+use crate::api::{ error::ApiError, paper::ledger::LedgerState };
+use super::{ circuit_breaker::BreakerState, market::{ ClosedTrade, PairPosition, Position } };
+
+const POSITIONS_FILE_NAME: &str = "positions.json";
+const PAIR_POSITIONS_FILE_NAME: &str = "pair_positions.json";
+const CLOSED_TRADES_FILE_NAME: &str = "closed_trades.json";
+const CIRCUIT_BREAKER_FILE_NAME: &str = "circuit_breaker.json";
+const PAPER_LEDGER_FILE_NAME: &str = "paper_ledger.json";
+
+/// JSON files keeping the open positions and the trading state across restarts
+pub struct PositionStore {
+    data_directory: PathBuf,
+}
+
+impl PositionStore {
+    pub fn new(data_directory: &Path) -> Self {
+        Self { data_directory: data_directory.to_path_buf() }
+    }
+
+    pub fn load(&self) -> Result<Vec<Position>, ApiError> {
+        self.read(POSITIONS_FILE_NAME)
+    }
+
+    pub fn load_pairs(&self) -> Result<Vec<PairPosition>, ApiError> {
+        self.read(PAIR_POSITIONS_FILE_NAME)
+    }
+
+    pub fn load_trades(&self) -> Result<Vec<ClosedTrade>, ApiError> {
+        self.read(CLOSED_TRADES_FILE_NAME)
+    }
+
+    pub fn load_breaker_state(&self) -> Result<BreakerState, ApiError> {
+        self.read(CIRCUIT_BREAKER_FILE_NAME)
+    }
+
+    /// Paper trading balances, `None` before the first paper order was filled
+    pub fn load_ledger(&self) -> Result<Option<LedgerState>, ApiError> {
+        self.read(PAPER_LEDGER_FILE_NAME)
+    }
+
+    pub fn save<'a>(&self, positions: impl Iterator<Item = &'a Position>) -> Result<(), ApiError> {
+        self.write(POSITIONS_FILE_NAME, &positions.collect::<Vec<_>>())
+    }
+
+    pub fn save_pairs<'a>(
+        &self,
+        positions: impl Iterator<Item = &'a PairPosition>
+    ) -> Result<(), ApiError> {
+        self.write(PAIR_POSITIONS_FILE_NAME, &positions.collect::<Vec<_>>())
+    }
+
+    pub fn save_trades<'a>(
+        &self,
+        trades: impl Iterator<Item = &'a ClosedTrade>
+    ) -> Result<(), ApiError> {
+        self.write(CLOSED_TRADES_FILE_NAME, &trades.collect::<Vec<_>>())
+    }
+
+    pub fn save_breaker_state(&self, state: &BreakerState) -> Result<(), ApiError> {
+        self.write(CIRCUIT_BREAKER_FILE_NAME, state)
+    }
+
+    pub fn save_ledger(&self, state: &LedgerState) -> Result<(), ApiError> {
+        self.write(PAPER_LEDGER_FILE_NAME, state)
+    }
+
+    /// Reads a stored file, a missing file holding the default value
+    fn read<T: DeserializeOwned + Default>(&self, file_name: &str) -> Result<T, ApiError> {
+        let content = match fs::read_to_string(self.data_directory.join(file_name)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Ok(T::default());
+            }
+            Err(e) => {
+                return Err(e.into());
+            }
+        };
+
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Replaces a stored file, writing through a temporary file so a crash
+    /// during the write never leaves a truncated file behind
+    fn write<T: Serialize>(&self, file_name: &str, value: &T) -> Result<(), ApiError> {
+        let content = serde_json::to_string_pretty(value)?;
+
+        fs::create_dir_all(&self.data_directory)?;
+
+        let file_path = self.data_directory.join(file_name);
+        let temporary_path = file_path.with_extension("json.tmp");
+
+        fs::write(&temporary_path, content)?;
+        fs::rename(&temporary_path, &file_path)?;
+
+        debug!("Saved {}", file_path.display());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::UtcDateTime;
+
+    use super::*;
+
+    #[test]
+    fn positions_round_trip() {
+        let directory = tempfile::tempdir().unwrap();
+        let store = PositionStore::new(&directory.path().join("data"));
+
+        assert!(store.load().unwrap().is_empty());
+
+        let positions = [
+            Position::new("BTCUSDT".to_string(), 100_f64, 0.5, UtcDateTime::now()),
+            Position::new("BTCUSDT".to_string(), 90_f64, 0.25, UtcDateTime::now()),
+        ];
+
+        store.save(positions.iter()).unwrap();
+
+        let restored = store.load().unwrap();
+
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored[0].id, positions[0].id);
+        assert_eq!(restored[0].timestamp, positions[0].timestamp);
+        assert_eq!(restored[1].entry_price, 90_f64);
+
+        store.save(restored[1..].iter()).unwrap();
+
+        assert_eq!(store.load().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn pair_positions_are_stored_separately() {
+        let directory = tempfile::tempdir().unwrap();
+        let store = PositionStore::new(directory.path());
+
+        let pair_position = PairPosition {
+            id: uuid::Uuid::new_v4(),
+            pair: "BTCUSDT/ETHUSDT".to_string(),
+            hedge_ratio: 20_f64,
+            legs: Vec::new(),
+            timestamp: UtcDateTime::now(),
+        };
+
+        store.save_pairs([pair_position].iter()).unwrap();
+
+        assert!(store.load().unwrap().is_empty());
+        assert_eq!(store.load_pairs().unwrap()[0].pair, "BTCUSDT/ETHUSDT");
+    }
+
+    #[test]
+    fn closed_trades_round_trip() {
+        let directory = tempfile::tempdir().unwrap();
+        let store = PositionStore::new(directory.path());
+
+        assert!(store.load_trades().unwrap().is_empty());
+
+        let trade = ClosedTrade {
+            position_id: uuid::Uuid::new_v4(),
+            symbol: "BTCUSDT".to_string(),
+            cost: 100_f64,
+            proceeds: 95_f64,
+            timestamp: UtcDateTime::now(),
+        };
+
+        store.save_trades([trade].iter()).unwrap();
+
+        let restored = store.load_trades().unwrap();
+
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].profit(), -5_f64);
+        assert!(store.load().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,362 @@
+use rust_decimal::{ prelude::{ FromPrimitive, ToPrimitive }, Decimal };
+use tracing::{ debug, info };
+
+use crate::strategy::{
+    mean_calculation::{ MaTracker, MeanCalculation },
+    strategy::{ RegimeFilter, Strategy, TrailingStopMode },
+    timeframe::duration_from_kline_interval,
+};
+use super::{
+    market::{ CandleAggregator, ProcessedCandle },
+    math::{ Math, TradingMath },
+};
+
+pub const MA_PERIOD_DIFFERENCE: usize = 3;
+const MIN_REGIME_CANDLES: usize = 20;
+
+/// Indicators of a symbol calculated from its candle history after each new candle
+#[derive(Debug, Clone, Copy)]
+pub struct Indicators {
+    /// Short/long moving average gap in percent
+    pub deviation: f64,
+    /// Long moving average the price reverts to
+    pub mean: f64,
+    pub z_score: f64,
+    pub bollinger_bands: Option<BollingerBands>,
+    pub rsi: Option<f64>,
+    pub regime: Option<Regime>,
+    /// Average true range over `risk_management.atr.period` candles
+    pub atr: Option<f64>,
+    /// Long Chandelier Exit over `risk_management.atr.period` candles, only calculated for
+    /// the `atr` trailing stop
+    pub chandelier_exit: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BollingerBands {
+    pub upper: f64,
+    pub middle: f64,
+    pub lower: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Regime {
+    pub hurst_exponent: f64,
+    pub adf_statistic: f64,
+    /// Bars for a deviation from the mean to halve, `None` when the series does not revert
+    pub half_life: Option<f64>,
+}
+
+impl Regime {
+    /// The series reverts to its mean when it is anti-persistent and the ADF test rejects
+    /// a unit root
+    pub fn is_mean_reverting(&self, filter: &RegimeFilter) -> bool {
+        self.hurst_exponent <= f64::from(filter.max_hurst) &&
+            self.adf_statistic <= f64::from(filter.adf_critical_value)
+    }
+}
+
+/// Moving averages and candle history of a single traded symbol, kept on `interval` candles
+/// aggregated from the `tick` candles the bot trades on
+pub struct SymbolTracker {
+    pub strategy: Strategy,
+    long_ma: MaTracker,
+    short_ma: MaTracker,
+    candles: Vec<ProcessedCandle>,
+    aggregator: CandleAggregator,
+    last_candle_timestamp: Option<u64>,
+}
+
+impl SymbolTracker {
+    pub fn new(strategy: Strategy) -> Self {
+        let long_period = strategy.timeframe.period_measurement.measure_bars;
+        let short_period = long_period / MA_PERIOD_DIFFERENCE;
+        let method = strategy.timeframe.period_measurement.mean_calculation_method;
+        let interval = duration_from_kline_interval(&strategy.timeframe.interval);
+
+        Self {
+            long_ma: MaTracker::new(long_period, method),
+            short_ma: MaTracker::new(short_period, method),
+            candles: Vec::new(),
+            aggregator: CandleAggregator::new(interval, strategy.timeframe.tick),
+            last_candle_timestamp: None,
+            strategy,
+        }
+    }
+
+    pub fn history_size(&self) -> usize {
+        let ma_history = self.strategy.timeframe.period_measurement.measure_bars *
+            MA_PERIOD_DIFFERENCE;
+
+        let measurement_deviation = &self.strategy.measurement_deviation;
+        let rsi_history = measurement_deviation.rsi
+            .as_ref()
+            .map_or(0, |rsi| rsi.period * MA_PERIOD_DIFFERENCE);
+
+        let regime_history = self.strategy.regime_filter
+            .as_ref()
+            .map_or(0, |filter| filter.lookback);
+        let atr_history = self.strategy.risk_management.atr.period * MA_PERIOD_DIFFERENCE;
+
+        ma_history
+            .max(measurement_deviation.bollinger.period)
+            .max(rsi_history)
+            .max(regime_history)
+            .max(atr_history)
+    }
+
+    pub fn seed(&mut self, candles: Vec<ProcessedCandle>) {
+        self.candles = candles
+            .into_iter()
+            .inspect(|candle| {
+                self.long_ma.update(candle);
+            })
+            .collect();
+
+        if let Some(latest_candles) = self.candles.chunks(MA_PERIOD_DIFFERENCE).last() {
+            for candle in latest_candles.iter() {
+                self.short_ma.update(candle);
+            }
+        }
+
+        self.candles.iter().for_each(|candle| {
+            self.long_ma.update(candle);
+        });
+
+        // The last tick of the latest seeded candle counts as processed, so polling right
+        // after the start does not feed it again
+        let interval = duration_from_kline_interval(&self.strategy.timeframe.interval).as_secs();
+        let tick = self.strategy.timeframe.tick.as_secs();
+        self.last_candle_timestamp = self.candles
+            .last()
+            .map(|candle| candle.timestamp + interval.saturating_sub(tick));
+
+        info!(
+            "Initialized {} with {} candles",
+            self.strategy.symbol,
+            self.candles.len()
+        );
+
+        debug!("Opening candles data for {}: {:?}", self.strategy.symbol, self.candles);
+
+        info!(
+            "Strating {} with {{short_ma: {}, long_ma: {} }}",
+            self.strategy.symbol,
+            self.short_ma.calculate(),
+            self.long_ma.calculate()
+        );
+    }
+
+    /// Feeds the `tick` candles of the forming `interval` candle that closed before the bot
+    /// started, so the first aggregated candle is complete
+    pub fn prime(&mut self, candles: &[ProcessedCandle]) {
+        for candle in candles {
+            self.aggregate(candle);
+        }
+
+        debug!("Primed {} with {} tick candles", self.strategy.symbol, candles.len());
+    }
+
+    /// Adds a `tick` candle missed by the trading cycles to the history
+    pub fn backfill(&mut self, candle: &ProcessedCandle) {
+        self.aggregate(candle);
+    }
+
+    /// Adds a new `tick` candle, returning the indicators of its close against the `interval`
+    /// candle history
+    pub fn update(&mut self, candle: ProcessedCandle) -> Indicators {
+        let price = candle.close;
+
+        self.aggregate(&candle);
+
+        let short_ma = self.short_ma.calculate();
+        let long_ma = self.long_ma.calculate();
+
+        Indicators {
+            deviation: ((short_ma - long_ma) / long_ma) * 100_f64,
+            mean: long_ma,
+            z_score: self.z_score(price),
+            bollinger_bands: self.bollinger_bands(),
+            rsi: self.rsi(),
+            regime: self.regime(),
+            atr: self.atr(),
+            chandelier_exit: self.chandelier_exit(),
+        }
+    }
+
+    pub fn last_candle_timestamp(&self) -> Option<u64> {
+        self.last_candle_timestamp
+    }
+
+    /// Adds a `tick` candle to the forming `interval` candle, updating the moving averages
+    /// and the history with every `interval` candle it completes
+    fn aggregate(&mut self, candle: &ProcessedCandle) {
+        self.last_candle_timestamp = Some(candle.timestamp);
+
+        for interval_candle in self.aggregator.push(candle) {
+            self.update_moving_averages(interval_candle);
+        }
+    }
+
+    /// Adds a new `interval` candle to the moving averages and the history
+    fn update_moving_averages(&mut self, candle: ProcessedCandle) {
+        let short_ma = self.short_ma.update(&candle);
+        let long_ma = self.long_ma.update(&candle);
+
+        self.insert_candle(candle);
+
+        info!(
+            "Updated {} MA: {{short: {}, long: {}}}",
+            self.strategy.symbol,
+            short_ma,
+            long_ma
+        );
+    }
+
+    /// Z-score of `price` against the mean and standard deviation of the last `measure_bars`
+    /// closes, 0 without any candle history
+    fn z_score(&self, price: f64) -> f64 {
+        let closes = self.recent_closes(self.strategy.timeframe.period_measurement.measure_bars);
+
+        TradingMath::calculate_z_score(
+            Decimal::from_f64(price).unwrap_or_default(),
+            TradingMath::calculate_mean(&closes),
+            TradingMath::calculate_standard_deviation(&to_decimals(&closes))
+        )
+            .to_f64()
+            .unwrap_or_default()
+    }
+
+    /// Bollinger Bands of the last `bollinger.period` closes, `None` until enough candles
+    /// were received
+    fn bollinger_bands(&self) -> Option<BollingerBands> {
+        let settings = &self.strategy.measurement_deviation.bollinger;
+        let closes = to_decimals(&self.recent_closes(settings.period));
+
+        let (upper, middle, lower) = TradingMath::calculate_bollinger_bands(
+            &closes,
+            settings.period,
+            Decimal::from_f32(settings.band_width).unwrap_or_default()
+        );
+
+        Some(BollingerBands {
+            upper: upper.last()?.to_f64()?,
+            middle: middle.last()?.to_f64()?,
+            lower: lower.last()?.to_f64()?,
+        })
+    }
+
+    /// Wilder's RSI over the candle history, `None` without a configured RSI filter or
+    /// until enough candles were received
+    fn rsi(&self) -> Option<f64> {
+        let period = self.strategy.measurement_deviation.rsi.as_ref()?.period;
+        let closes = to_decimals(&self.recent_closes(self.candles.len()));
+
+        TradingMath::calculate_rsi(&closes, period).last()?.to_f64()
+    }
+
+    /// Wilder's ATR over the candle history, `None` until enough candles were received
+    fn atr(&self) -> Option<f64> {
+        let (high, low, close) = self.price_series();
+
+        TradingMath::calculate_atr(&high, &low, &close, self.strategy.risk_management.atr.period)
+            .last()?
+            .to_f64()
+    }
+
+    /// Highest high of the last `atr.period` candles minus `trailing_stop.atr_multiplier`
+    /// ATRs, `None` without an `atr` trailing stop or until enough candles were received
+    fn chandelier_exit(&self) -> Option<f64> {
+        let risk_management = &self.strategy.risk_management;
+        let trailing_stop = risk_management.trailing_stop
+            .as_ref()
+            .filter(|trailing_stop| trailing_stop.mode == TrailingStopMode::Atr)?;
+        let (high, low, close) = self.price_series();
+
+        let (long_exits, _) = TradingMath::calculate_chandelier_exit(
+            &high,
+            &low,
+            &close,
+            risk_management.atr.period,
+            Decimal::from_f32(trailing_stop.atr_multiplier)?
+        );
+
+        long_exits.last()?.to_f64()
+    }
+
+    /// Highs, lows and closes of the candle history, oldest first
+    fn price_series(&self) -> (Vec<Decimal>, Vec<Decimal>, Vec<Decimal>) {
+        let (high, low): (Vec<f64>, Vec<f64>) = self.candles
+            .iter()
+            .map(|candle| (candle.high, candle.low))
+            .unzip();
+        let close = self.recent_closes(self.candles.len());
+
+        (to_decimals(&high), to_decimals(&low), to_decimals(&close))
+    }
+
+    /// Regime statistics of the last `regime_filter.lookback` closes, or of the whole history
+    /// without a configured regime filter
+    fn regime(&self) -> Option<Regime> {
+        let lookback = self.strategy.regime_filter
+            .as_ref()
+            .map_or(self.candles.len(), |filter| filter.lookback);
+        let closes = to_decimals(&self.recent_closes(lookback));
+
+        if closes.len() < MIN_REGIME_CANDLES {
+            return None;
+        }
+
+        let half_life = TradingMath::calculate_half_life(&closes);
+
+        Some(Regime {
+            hurst_exponent: TradingMath::calculate_hurst_exponent(&closes).to_f64()?,
+            adf_statistic: TradingMath::calculate_adf_statistic(&closes).to_f64()?,
+            half_life: (half_life != Decimal::MAX).then(|| half_life.to_f64()).flatten(),
+        })
+    }
+
+    /// Closes of the latest `count` candles, oldest first
+    fn recent_closes(&self, count: usize) -> Vec<f64> {
+        let start = self.candles.len().saturating_sub(count);
+
+        self.candles[start..]
+            .iter()
+            .map(|candle| candle.close)
+            .collect()
+    }
+
+    fn insert_candle(&mut self, candle: ProcessedCandle) {
+        self.candles.push(candle);
+
+        if self.candles.len() > self.history_size() {
+            self.candles.remove(0);
+        }
+    }
+}
+
+fn to_decimals(values: &[f64]) -> Vec<Decimal> {
+    values
+        .iter()
+        .filter_map(|value| Decimal::from_f64(*value))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::strategy::strategy;
+
+    fn candle(timestamp: u64, close: f64) -> ProcessedCandle {
+        ProcessedCandle { timestamp, open: close, high: close, low: close, close, volume: 1_f64 }
+    }
+
+    #[test]
+    fn z_score_without_history_is_zero() {
+        let mut tracker = SymbolTracker::new(strategy(""));
+
+        tracker.seed(Vec::new());
+
+        assert_eq!(tracker.update(candle(3600, 100_f64)).z_score, 0_f64);
+    }
+}
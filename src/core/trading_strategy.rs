@@ -1,15 +1,61 @@
-use std::collections::HashSet;
+use std::{ collections::HashSet, time::Duration };
 
-use tracing::{ error, info, debug, trace };
+use rust_decimal::{ prelude::{ FromPrimitive, ToPrimitive }, Decimal };
+use tracing::{ error, info, debug, trace, warn };
 use uuid::Uuid;
 
-use crate::{ api::{ client::ApiClient, error::ApiError }, strategy::strategy::Strategy };
-use super::position_manager::PositionManager;
+use crate::{
+    api::{ client::ApiClient, error::ApiError },
+    strategy::strategy::{
+        BollingerExitBand,
+        KellySizing,
+        PairStrategy,
+        PositionSizing,
+        ProfitExit,
+        SignalMode,
+        Strategy,
+        TrailingStopMode,
+    },
+};
+use super::{
+    market::{ ClosedTrade, LegSide, Position },
+    math::{ Math, TradingMath },
+    pair_tracker::PairSignal,
+    position_manager::{ LegOrder, PositionManager },
+    position_store::PositionStore,
+    tracker::Indicators,
+};
 
 pub struct TradingStrategy {
     position_manager: PositionManager,
 }
 
+/// Market state and dependencies required to evaluate signals during one trading cycle,
+/// `strategy` being the resolved strategy of the symbol the cycle runs for
+pub struct CycleContext<'a> {
+    pub current_price: f64,
+    /// Open time of the candle the cycle runs for in seconds
+    pub candle_timestamp: u64,
+    pub indicators: Indicators,
+    pub account_balance: f64,
+    pub max_open_positions: usize,
+    pub strategy: &'a Strategy,
+    pub api_client: &'a dyn ApiClient,
+}
+
+/// Spread state and dependencies required to trade a pair during one trading cycle,
+/// `strategy` being the top-level strategy
+pub struct PairCycleContext<'a> {
+    pub pair: &'a PairStrategy,
+    pub signal: PairSignal,
+    /// `false` while the drawdown circuit breaker halts new entries
+    pub allow_entries: bool,
+    pub account_balance: f64,
+    pub max_open_positions: usize,
+    pub strategy: &'a Strategy,
+    pub api_client: &'a dyn ApiClient,
+}
+
 impl TradingStrategy {
     pub fn new(max_positions: usize) -> Self {
         Self {
@@ -17,104 +63,893 @@ impl TradingStrategy {
         }
     }
 
-    pub async fn check_entry_signals(
-        &mut self,
-        current_price: f64,
-        deviation: f64,
-        account_balance: f64,
-        strategy: &'_ Strategy,
-        api_client: &'_ dyn ApiClient
-    ) -> Result<f64, ApiError> {
-        if self.position_manager.len() >= strategy.risk_management.max_positions {
+    pub fn with_position_store(mut self, store: PositionStore) -> Self {
+        self.position_manager = self.position_manager.with_store(store);
+        self
+    }
+
+    pub fn restore_positions(&mut self) -> Result<usize, ApiError> {
+        self.position_manager.restore_positions()
+    }
+
+    pub async fn check_entry_signals(&mut self, context: &CycleContext<'_>) -> Result<f64, ApiError> {
+        let strategy = context.strategy;
+
+        if self.position_manager.len() >= context.max_open_positions {
             info!("Max positions reached, not opening new positions");
             return Ok(0_f64);
         }
 
-        if deviation <= -f64::from(strategy.measurement_deviation.enter_deviation) {
-            info!("Entry signal detected! Deviation: {:.2}%", deviation);
+        if
+            self.position_manager.symbol_positions_count(&strategy.symbol) >=
+            strategy.risk_management.max_positions
+        {
+            info!("Max positions reached for {}, not opening new positions", strategy.symbol);
+            return Ok(0_f64);
+        }
+
+        if Self::is_entry_signal(context) && Self::is_entry_confirmed(context) {
+            info!(
+                "Entry signal detected! Deviation: {:.2}%, z-score: {:.2}",
+                context.indicators.deviation,
+                context.indicators.z_score
+            );
 
-            let capital_to_use =
-                account_balance * f64::from(strategy.risk_management.capital_per_trade);
-            let quantity = capital_to_use / current_price;
+            let quantity = self.position_quantity(context);
+            let stop_distance = Self::atr_stop_distance(context);
 
             self.position_manager
-                .open_position(&strategy.symbol, quantity, current_price, api_client).await
+                .open_position(quantity, stop_distance, context).await
                 .map(|sum| -sum)
         } else {
             Ok(0_f64)
         }
     }
 
-    pub async fn check_exit_signals(
+    pub async fn check_exit_signals(&mut self, context: &CycleContext<'_>) -> Result<f64, ApiError> {
+        let strategy = context.strategy;
+
+        if self.position_manager.symbol_positions_count(&strategy.symbol) == 0 {
+            return Ok(0_f64);
+        }
+
+        self.position_manager.update_peak_prices(&strategy.symbol, context.current_price);
+
+        let positions_to_close: HashSet<Uuid> = self.position_manager
+            .get_symbol_positions(&strategy.symbol)
+            .filter(|position| Self::is_exit_signal(position, context))
+            .map(|position| position.id)
+            .collect();
+
+        Ok(self.close_positions(positions_to_close, context).await)
+    }
+
+    /// Closes every open position of the symbol regardless of its signals
+    pub async fn liquidate_positions(&mut self, context: &CycleContext<'_>) -> f64 {
+        let positions_to_close: HashSet<Uuid> = self.position_manager
+            .get_symbol_positions(&context.strategy.symbol)
+            .map(|position| position.id)
+            .collect();
+
+        if !positions_to_close.is_empty() {
+            warn!(
+                "Liquidating {} open {} positions",
+                positions_to_close.len(),
+                context.strategy.symbol
+            );
+        }
+
+        self.close_positions(positions_to_close, context).await
+    }
+
+    async fn close_positions(
         &mut self,
-        current_price: f64,
-        deviation: f64,
-        strategy: &'_ Strategy,
-        api_client: &'_ dyn ApiClient
+        positions_to_close: HashSet<Uuid>,
+        context: &CycleContext<'_>
+    ) -> f64 {
+        let mut balance_difference = 0_f64;
+
+        for position_id in positions_to_close {
+            match self.position_manager.close_position(position_id, context).await {
+                Ok(sum) => {
+                    balance_difference += sum;
+                }
+                Err(e) =>
+                    error!(
+                        "Could not create an order to sell for position {:?}: {}",
+                        position_id,
+                        e
+                    ),
+            };
+        }
+
+        balance_difference
+    }
+
+    /// Trades the spread of a pair, buying the cheap symbol and selling the expensive one
+    /// once the spread z-score passes `entry_z_score`, and closing both legs once it reverts
+    /// within `exit_z_score` or the combined loss reaches `stop_loss`
+    pub async fn check_pair_signals(
+        &mut self,
+        context: &PairCycleContext<'_>
+    ) -> Result<f64, ApiError> {
+        let pair = context.pair;
+        let signal = &context.signal;
+
+        if let Some(position) = self.position_manager.get_pair_position(&pair.name()) {
+            let profit_percentage = position.pnl_percentage(|symbol| signal.price_of(pair, symbol));
+            let is_reverted = signal.z_score.abs() <= f64::from(pair.exit_z_score);
+            let is_stop_loss =
+                profit_percentage <= -f64::from(context.strategy.risk_management.stop_loss);
+
+            if !is_reverted && !is_stop_loss {
+                return Ok(0_f64);
+            }
+
+            info!(
+                "Pair exit triggered for {} at z-score {:.2}, closing with profit: {:.2}%",
+                pair.name(),
+                signal.z_score,
+                profit_percentage
+            );
+
+            let position_id = position.id;
+
+            return self.position_manager.close_pair_position(position_id, context).await;
+        }
+
+        if !context.allow_entries {
+            return Ok(0_f64);
+        }
+
+        if self.position_manager.len() >= context.max_open_positions {
+            info!("Max positions reached, not opening new pair positions");
+            return Ok(0_f64);
+        }
+
+        if signal.z_score.abs() < f64::from(pair.entry_z_score) {
+            return Ok(0_f64);
+        }
+
+        if signal.hedge_ratio <= 0_f64 {
+            warn!("Ignoring {} entry signal for non-positive hedge ratio", pair.name());
+            return Ok(0_f64);
+        }
+
+        info!(
+            "Pair entry signal detected for {}! z-score: {:.2}, hedge ratio: {:.4}",
+            pair.name(),
+            signal.z_score,
+            signal.hedge_ratio
+        );
+
+        self.position_manager.open_pair_position(Self::pair_orders(context), context).await
+    }
+
+    /// Closes the open position of the pair regardless of its signal
+    pub async fn liquidate_pair(
+        &mut self,
+        context: &PairCycleContext<'_>
     ) -> Result<f64, ApiError> {
-        if self.position_manager.is_empty() {
+        let Some(position) = self.position_manager.get_pair_position(&context.pair.name()) else {
             return Ok(0_f64);
+        };
+
+        warn!("Liquidating open pair position {}", context.pair.name());
+
+        let position_id = position.id;
+
+        self.position_manager.close_pair_position(position_id, context).await
+    }
+
+    /// Orders of both legs, one unit of the first symbol against `hedge_ratio` units of
+    /// the second one, longing the first symbol while the spread is below its mean
+    fn pair_orders(context: &PairCycleContext<'_>) -> Vec<LegOrder> {
+        let pair = context.pair;
+        let signal = &context.signal;
+
+        let capital_to_use = context.account_balance * f64::from(pair.capital_per_trade);
+        let first_quantity = capital_to_use / signal.first_price;
+
+        let (first_side, second_side) = if signal.z_score < 0_f64 {
+            (LegSide::Long, LegSide::Short)
+        } else {
+            (LegSide::Short, LegSide::Long)
+        };
+
+        vec![
+            LegOrder {
+                symbol: pair.first_symbol.clone(),
+                side: first_side,
+                quantity: first_quantity,
+                price: signal.first_price,
+            },
+            LegOrder {
+                symbol: pair.second_symbol.clone(),
+                side: second_side,
+                quantity: first_quantity * signal.hedge_ratio,
+                price: signal.second_price,
+            }
+        ]
+    }
+
+    /// Quantity of a new position according to `risk_management.position_sizing`
+    fn position_quantity(&self, context: &CycleContext<'_>) -> f64 {
+        let risk_management = &context.strategy.risk_management;
+        let fixed_quantity = (context.account_balance *
+            f64::from(risk_management.capital_per_trade)) / context.current_price;
+
+        match risk_management.position_sizing {
+            PositionSizing::Fixed => fixed_quantity,
+            PositionSizing::Kelly => {
+                let Some(capital) = self.kelly_capital(&risk_management.kelly) else {
+                    info!("Not enough closed trades yet, sizing position with capital_per_trade");
+                    return fixed_quantity;
+                };
+
+                info!("Kelly criterion sized position to {:.2}% of the balance", capital * 100_f64);
+
+                (context.account_balance * capital) / context.current_price
+            }
+            PositionSizing::Atr => {
+                let Some(stop_distance) = Self::atr_stop_distance(context) else {
+                    warn!("ATR is not available yet, sizing position with capital_per_trade");
+                    return fixed_quantity;
+                };
+
+                let quantity = TradingMath::calculate_optimal_position_size(
+                    to_decimal(context.current_price),
+                    to_decimal(stop_distance / context.current_price),
+                    to_decimal(context.account_balance),
+                    to_decimal(f64::from(risk_management.atr.risk_per_trade))
+                );
+
+                info!("ATR sized position to {} for stop distance {:.4}", quantity, stop_distance);
+
+                quantity.to_f64().unwrap_or_default()
+            }
+        }
+    }
+
+    /// Distance of the stop the `atr` position sizing risks `risk_per_trade` against,
+    /// `atr.stop_multiplier` ATRs below the entry. `None` for other sizings or until the ATR is
+    /// available, positions are then sized with `capital_per_trade` and have no ATR stop
+    fn atr_stop_distance(context: &CycleContext<'_>) -> Option<f64> {
+        let risk_management = &context.strategy.risk_management;
+
+        if risk_management.position_sizing != PositionSizing::Atr {
+            return None;
+        }
+
+        let atr = context.indicators.atr.filter(|atr| *atr > 0_f64)?;
+
+        Some(atr * f64::from(risk_management.atr.stop_multiplier))
+    }
+
+    /// Fraction of the balance to spend according to the Kelly criterion of the latest
+    /// `window` closed trades, `None` until `min_trades` trades were closed and while none
+    /// of them lost, as the payoff ratio is unknown without a loss
+    fn kelly_capital(&self, kelly: &KellySizing) -> Option<f64> {
+        let profits: Vec<f64> = self.position_manager
+            .recent_trades(kelly.window)
+            .map(ClosedTrade::profit)
+            .collect();
+
+        if profits.is_empty() || profits.len() < kelly.min_trades {
+            return None;
+        }
+
+        let (wins, losses): (Vec<f64>, Vec<f64>) = profits
+            .iter()
+            .partition(|profit| **profit > 0_f64);
+
+        let win_probability = (wins.len() as f64) / (profits.len() as f64);
+        let average_win = wins.iter().sum::<f64>() / (wins.len().max(1) as f64);
+        let average_loss = losses.iter().sum::<f64>().abs() / (losses.len().max(1) as f64);
+
+        if average_loss == 0_f64 {
+            debug!("No losing trade among the last {} trades, using fixed sizing", profits.len());
+
+            return None;
         }
 
-        let mut positions_to_close: HashSet<Uuid> = HashSet::new();
+        let kelly_fraction = TradingMath::calculate_kelly_criterion(
+            to_decimal(win_probability),
+            to_decimal(average_win / average_loss)
+        )
+            .to_f64()
+            .unwrap_or_default();
 
-        self.position_manager.get_positions().for_each(|position| {
-            let profit_percentage =
-                ((current_price - position.entry_price) / position.entry_price) * 100_f64;
-            trace!("Profit percentage for position {:?}: {:.2}%", position.id, profit_percentage);
+        debug!(
+            "Kelly criterion {:.4} from {} trades with win probability {:.2}",
+            kelly_fraction,
+            profits.len(),
+            win_probability
+        );
 
-            if profit_percentage <= -f64::from(strategy.risk_management.stop_loss) {
+        Some(
+            (kelly_fraction * f64::from(kelly.fraction)).clamp(
+                f64::from(kelly.min_capital),
+                f64::from(kelly.max_capital)
+            )
+        )
+    }
+
+    fn is_entry_signal(context: &CycleContext<'_>) -> bool {
+        let measurement_deviation = &context.strategy.measurement_deviation;
+        let indicators = &context.indicators;
+
+        match measurement_deviation.signal_mode {
+            SignalMode::Deviation =>
+                indicators.deviation <= -f64::from(measurement_deviation.enter_deviation),
+            SignalMode::ZScore =>
+                indicators.z_score <= -f64::from(measurement_deviation.zscore.entry_threshold),
+            SignalMode::Bollinger =>
+                indicators.bollinger_bands.is_some_and(|bands| context.current_price < bands.lower),
+        }
+    }
+
+    /// Checks the optional RSI filter, entries are confirmed only while the RSI is oversold
+    fn is_entry_confirmed(context: &CycleContext<'_>) -> bool {
+        let Some(rsi_filter) = &context.strategy.measurement_deviation.rsi else {
+            return true;
+        };
+
+        match context.indicators.rsi {
+            Some(rsi) if rsi < f64::from(rsi_filter.oversold) => true,
+            rsi => {
                 info!(
-                    "Stop loss triggered, closing position {} with loss: {:.2}%",
-                    position.id,
-                    profit_percentage
+                    "Entry signal ignored, RSI {:?} is not below oversold level {}",
+                    rsi,
+                    rsi_filter.oversold
                 );
 
-                positions_to_close.insert(position.id);
-            } else if deviation >= strategy.risk_management.profit_level.into() {
+                false
+            }
+        }
+    }
+
+    fn is_rsi_exit(context: &CycleContext<'_>) -> bool {
+        let overbought = context.strategy.measurement_deviation.rsi
+            .as_ref()
+            .and_then(|rsi_filter| rsi_filter.overbought);
+
+        match (context.indicators.rsi, overbought) {
+            (Some(rsi), Some(overbought)) => rsi >= f64::from(overbought),
+            _ => false,
+        }
+    }
+
+    fn is_exit_signal(position: &Position, context: &CycleContext<'_>) -> bool {
+        let strategy = context.strategy;
+
+        let profit_percentage =
+            ((context.current_price - position.entry_price) / position.entry_price) * 100_f64;
+        trace!("Profit percentage for position {:?}: {:.2}%", position.id, profit_percentage);
+
+        if profit_percentage <= -f64::from(strategy.risk_management.stop_loss) {
+            info!(
+                "Stop loss triggered, closing position {} with loss: {:.2}%",
+                position.id,
+                profit_percentage
+            );
+
+            return true;
+        }
+
+        if position.stop_price.is_some_and(|stop_price| context.current_price <= stop_price) {
+            info!(
+                "ATR stop triggered at {:.4}, closing position {} with loss: {:.2}%",
+                position.stop_price.unwrap_or_default(),
+                position.id,
+                profit_percentage
+            );
+
+            return true;
+        }
+
+        if let Some(stop_price) = Self::trailing_stop_price(position, context) {
+            if context.current_price <= stop_price {
                 info!(
-                    "Profit triggered, closing position {} with gained profit: {:.2}%",
+                    "Trailing stop triggered at {:.4} from peak {:.4}, closing position {} with \
+                     profit: {:.2}%",
+                    stop_price,
+                    position.peak_price(),
                     position.id,
                     profit_percentage
                 );
 
-                positions_to_close.insert(position.id);
-            } else {
-                debug!("Checking deviation for extreme value: {:.2}", deviation);
+                return true;
+            }
+        }
+
+        if Self::is_holding_period_exceeded(position, context) {
+            info!(
+                "Max holding period exceeded, closing stale position {} opened at {} with \
+                 profit: {:.2}%",
+                position.id,
+                position.timestamp,
+                profit_percentage
+            );
+
+            return true;
+        }
+
+        if Self::is_rsi_exit(context) {
+            info!(
+                "RSI overbought exit triggered at {:.2}, closing position {} with profit: {:.2}%",
+                context.indicators.rsi.unwrap_or_default(),
+                position.id,
+                profit_percentage
+            );
+
+            return true;
+        }
 
-                if deviation >= strategy.risk_management.max_drawdown.into() {
+        match strategy.measurement_deviation.signal_mode {
+            SignalMode::Deviation => {}
+            SignalMode::ZScore => {
+                let exit_threshold = strategy.measurement_deviation.zscore.exit_threshold;
+                let is_reverted = context.indicators.z_score >= -f64::from(exit_threshold);
+
+                if is_reverted {
                     info!(
-                        "Mean Reversion exit triggered for unstable deviation, deviation: {:.2}%",
-                        deviation
+                        "Z-score exit triggered at {:.2}, closing position {} with profit: {:.2}%",
+                        context.indicators.z_score,
+                        position.id,
+                        profit_percentage
                     );
+                }
+
+                return is_reverted;
+            }
+            SignalMode::Bollinger => {
+                let is_reverted = Self::is_bollinger_exit(context);
 
-                    positions_to_close.insert(position.id);
+                if is_reverted {
+                    info!(
+                        "Bollinger exit triggered, closing position {} with profit: {:.2}%",
+                        position.id,
+                        profit_percentage
+                    );
                 }
+
+                return is_reverted;
             }
-        });
+        }
 
-        let mut balance_difference = 0_f64;
+        Self::is_profit_exit(position, profit_percentage, context)
+    }
 
-        for position_id in positions_to_close {
-            match
-                self.position_manager.close_position(position_id, current_price, api_client).await
-            {
-                Ok(sum) => {
-                    balance_difference += sum;
-                }
-                Err(e) =>
-                    error!(
-                        "Could not create an order to sell for position {:?}: {}",
-                        position_id,
-                        e
-                    ),
-            };
+    /// Checks the `profit_exit` of the deviation signal mode
+    fn is_profit_exit(
+        position: &Position,
+        profit_percentage: f64,
+        context: &CycleContext<'_>
+    ) -> bool {
+        let risk_management = &context.strategy.risk_management;
+        let indicators = &context.indicators;
+
+        let (is_exit, reason) = match risk_management.profit_exit {
+            ProfitExit::TakeProfit =>
+                (profit_percentage >= f64::from(risk_management.profit_level), "Take profit"),
+            ProfitExit::Mean => (context.current_price >= indicators.mean, "Revert to mean"),
+            ProfitExit::Deviation =>
+                (indicators.deviation >= f64::from(risk_management.profit_level), "Deviation"),
+        };
+
+        if is_exit {
+            info!(
+                "{} exit triggered, closing position {} with gained profit: {:.2}%",
+                reason,
+                position.id,
+                profit_percentage
+            );
         }
 
-        Ok(balance_difference)
+        is_exit
+    }
+
+    /// Checks the optional `max_holding_period` against the time between the close of the
+    /// candle the position was opened on and the close of the current one, a bar being one
+    /// `tick`. Entries are filled a moment after their candle closes, so the entry time is
+    /// rounded down to that close
+    fn is_holding_period_exceeded(position: &Position, context: &CycleContext<'_>) -> bool {
+        let strategy = context.strategy;
+        let Some(max_holding_period) = strategy.risk_management.max_holding_period else {
+            return false;
+        };
+
+        let tick = strategy.timeframe.tick.as_secs().max(1);
+        let filled_at = position.timestamp.unix_timestamp().max(0).unsigned_abs();
+        let opened_at = filled_at - (filled_at % tick);
+        let closed_at = context.candle_timestamp + tick;
+
+        let holding_period = Duration::from_secs(closed_at.saturating_sub(opened_at));
+
+        holding_period >= max_holding_period.duration(strategy.timeframe.tick)
+    }
+
+    /// Price the position is closed at by the optional trailing stop, `None` without
+    /// a configured trailing stop or until the Chandelier Exit is available. The Chandelier
+    /// Exit hangs from the highs of the last `atr.period` candles, which can predate the entry,
+    /// so it only applies once it is below the peak of the position
+    fn trailing_stop_price(position: &Position, context: &CycleContext<'_>) -> Option<f64> {
+        let trailing_stop = context.strategy.risk_management.trailing_stop.as_ref()?;
+        let peak_price = position.peak_price();
+
+        match trailing_stop.mode {
+            TrailingStopMode::Percentage =>
+                Some(peak_price * (1_f64 - f64::from(trailing_stop.distance) / 100_f64)),
+            TrailingStopMode::Atr =>
+                context.indicators.chandelier_exit.filter(|exit| *exit <= peak_price),
+        }
+    }
+
+    fn is_bollinger_exit(context: &CycleContext<'_>) -> bool {
+        let Some(bands) = context.indicators.bollinger_bands else {
+            return false;
+        };
+
+        match context.strategy.measurement_deviation.bollinger.exit_band {
+            BollingerExitBand::Middle => context.current_price >= bands.middle,
+            BollingerExitBand::Upper => context.current_price >= bands.upper,
+        }
     }
 
     pub fn open_positions_count(&self) -> usize {
         self.position_manager.len()
     }
+
+    pub fn open_positions_value(&self, price_of: impl Fn(&str) -> Option<f64>) -> f64 {
+        self.position_manager.open_value(price_of)
+    }
+}
+
+fn to_decimal(value: f64) -> Decimal {
+    Decimal::from_f64(value).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use time::UtcDateTime;
+
+    use super::*;
+    use crate::{
+        api::paper::ledger::Ledger,
+        backtest::client::BacktestApi,
+        core::tracker::BollingerBands,
+        tests::strategy::strategy,
+    };
+
+    fn indicators() -> Indicators {
+        Indicators {
+            deviation: 0_f64,
+            mean: 100_f64,
+            z_score: 0_f64,
+            bollinger_bands: None,
+            rsi: None,
+            regime: None,
+            atr: None,
+            chandelier_exit: None,
+        }
+    }
+
+    fn client(strategy: &Strategy) -> BacktestApi {
+        BacktestApi::new(
+            strategy.symbol.clone(),
+            Vec::new(),
+            strategy.timeframe.tick,
+            Ledger::new(1000_f64, &strategy.trading_symbol, &strategy.exchange.paper)
+        )
+    }
+
+    fn context<'a>(
+        strategy: &'a Strategy,
+        api_client: &'a dyn ApiClient,
+        current_price: f64,
+        indicators: Indicators
+    ) -> CycleContext<'a> {
+        CycleContext {
+            current_price,
+            candle_timestamp: 0,
+            indicators,
+            account_balance: 1000_f64,
+            max_open_positions: 5,
+            strategy,
+            api_client,
+        }
+    }
+
+    fn position(entry_price: f64) -> Position {
+        Position::new("BTCUSDT".to_string(), entry_price, 1_f64, UtcDateTime::UNIX_EPOCH)
+    }
+
+    #[test]
+    fn holding_period_counts_bars_between_candle_closes() {
+        let strategy = strategy("[risk_management]\nmax_holding_period = 3");
+        let client = client(&strategy);
+        let hour = 60 * 60;
+
+        // Entered on the candle opened at 10:00, filled shortly after its 11:00 close
+        let filled_at = UtcDateTime::from_unix_timestamp(11 * hour + 2).unwrap();
+        let position = Position::new("BTCUSDT".to_string(), 100_f64, 1_f64, filled_at);
+        let held = |bars: u64| CycleContext {
+            candle_timestamp: (10 + bars) * (hour as u64),
+            ..context(&strategy, &client, 100_f64, indicators())
+        };
+
+        assert!(!TradingStrategy::is_holding_period_exceeded(&position, &held(2)));
+        assert!(TradingStrategy::is_holding_period_exceeded(&position, &held(3)));
+    }
+
+    #[test]
+    fn atr_sizing_risks_a_fixed_fraction_at_the_atr_stop() {
+        let strategy = strategy(
+            "[risk_management]\nposition_sizing = \"atr\"\ncapital_per_trade = 0.5\n\
+             [risk_management.atr]\nperiod = 14\nstop_multiplier = 2\nrisk_per_trade = 0.01"
+        );
+        let client = client(&strategy);
+        let trading_strategy = TradingStrategy::new(5);
+        let with_atr = |atr| Indicators { atr, ..indicators() };
+
+        let context = context(&strategy, &client, 100_f64, with_atr(Some(2.5)));
+        let stop_distance = TradingStrategy::atr_stop_distance(&context).unwrap();
+        let quantity = trading_strategy.position_quantity(&context);
+
+        assert_eq!(stop_distance, 5_f64);
+        // Losing the stop distance on the whole quantity costs 1% of the 1000 balance
+        assert!((quantity * stop_distance - 10_f64).abs() < 1e-4);
+
+        let without_atr = CycleContext { indicators: with_atr(None), ..context };
+
+        assert_eq!(TradingStrategy::atr_stop_distance(&without_atr), None);
+        assert_eq!(trading_strategy.position_quantity(&without_atr), 5_f64);
+    }
+
+    #[test]
+    fn atr_stop_closes_position() {
+        let strategy = strategy("[risk_management]\nposition_sizing = \"atr\"");
+        let client = client(&strategy);
+        let mut position = position(100_f64);
+        position.stop_price = Some(96_f64);
+
+        let above = context(&strategy, &client, 96.5, indicators());
+        let below = context(&strategy, &client, 96_f64, indicators());
+
+        assert!(!TradingStrategy::is_exit_signal(&position, &above));
+        assert!(TradingStrategy::is_exit_signal(&position, &below));
+    }
+
+    #[test]
+    fn percentage_trailing_stop_follows_peak() {
+        let strategy = strategy("[risk_management.trailing_stop]\ndistance = 10");
+        let client = client(&strategy);
+        let mut position = position(100_f64);
+        position.highest_price = 120_f64;
+
+        let above = context(&strategy, &client, 109_f64, indicators());
+        let below = context(&strategy, &client, 107_f64, indicators());
+
+        assert_eq!(TradingStrategy::trailing_stop_price(&position, &above), Some(108_f64));
+        assert!(!TradingStrategy::is_exit_signal(&position, &above));
+        assert!(TradingStrategy::is_exit_signal(&position, &below));
+    }
+
+    #[test]
+    fn atr_trailing_stop_uses_chandelier_exit_below_peak() {
+        let strategy = strategy("[risk_management.trailing_stop]\nmode = \"atr\"");
+        let client = client(&strategy);
+        let mut position = position(100_f64);
+        position.highest_price = 110_f64;
+        let chandelier = |chandelier_exit| Indicators {
+            chandelier_exit: Some(chandelier_exit),
+            ..indicators()
+        };
+
+        let trailing = context(&strategy, &client, 104_f64, chandelier(105_f64));
+        let above_peak = context(&strategy, &client, 104_f64, chandelier(112_f64));
+
+        assert_eq!(TradingStrategy::trailing_stop_price(&position, &trailing), Some(105_f64));
+        assert!(TradingStrategy::is_exit_signal(&position, &trailing));
+        assert_eq!(TradingStrategy::trailing_stop_price(&position, &above_peak), None);
+        assert!(!TradingStrategy::is_exit_signal(&position, &above_peak));
+    }
+
+    #[test]
+    fn take_profit_exit_against_entry_price() {
+        let strategy = strategy(
+            "[risk_management]\nprofit_exit = \"take_profit\"\nprofit_level = 2"
+        );
+        let client = client(&strategy);
+        let position = position(100_f64);
+
+        let below = context(&strategy, &client, 101.5, indicators());
+        let above = context(&strategy, &client, 102.5, indicators());
+
+        assert!(!TradingStrategy::is_exit_signal(&position, &below));
+        assert!(TradingStrategy::is_exit_signal(&position, &above));
+    }
+
+    #[test]
+    fn mean_exit_once_price_reaches_long_average() {
+        let strategy = strategy("[risk_management]\nprofit_exit = \"mean\"");
+        let client = client(&strategy);
+        let position = position(90_f64);
+
+        let below = context(&strategy, &client, 99_f64, indicators());
+        let reverted = context(&strategy, &client, 100_f64, indicators());
+
+        assert!(!TradingStrategy::is_exit_signal(&position, &below));
+        assert!(TradingStrategy::is_exit_signal(&position, &reverted));
+    }
+
+    #[test]
+    fn deviation_exit_is_the_default() {
+        let strategy = strategy("[risk_management]\nprofit_level = 0.5");
+        let client = client(&strategy);
+        let position = position(100_f64);
+
+        let profitable = context(&strategy, &client, 150_f64, indicators());
+        let deviated = context(&strategy, &client, 100_f64, Indicators {
+            deviation: 0.5,
+            ..indicators()
+        });
+
+        assert!(!TradingStrategy::is_exit_signal(&position, &profitable));
+        assert!(TradingStrategy::is_exit_signal(&position, &deviated));
+    }
+
+    #[test]
+    fn deviation_entry_below_enter_deviation() {
+        let strategy = strategy("");
+        let client = client(&strategy);
+        let deviated = |deviation| Indicators { deviation, ..indicators() };
+
+        let shallow = context(&strategy, &client, 100_f64, deviated(-0.25));
+        let deep = context(&strategy, &client, 100_f64, deviated(-0.5));
+
+        assert!(!TradingStrategy::is_entry_signal(&shallow));
+        assert!(TradingStrategy::is_entry_signal(&deep));
+    }
+
+    #[test]
+    fn zscore_entry_and_exit_against_thresholds() {
+        let strategy = strategy("[measurement_deviation]\nsignal_mode = \"zscore\"");
+        let client = client(&strategy);
+        let position = position(100_f64);
+        // The deviation signal alone never enters in z-score mode
+        let scored = |z_score| Indicators { z_score, deviation: -10_f64, ..indicators() };
+
+        let above = context(&strategy, &client, 100_f64, scored(-1.5));
+        let below = context(&strategy, &client, 100_f64, scored(-2_f64));
+        let reverted = context(&strategy, &client, 100_f64, scored(0_f64));
+
+        assert!(!TradingStrategy::is_entry_signal(&above));
+        assert!(TradingStrategy::is_entry_signal(&below));
+        assert!(!TradingStrategy::is_exit_signal(&position, &above));
+        assert!(TradingStrategy::is_exit_signal(&position, &reverted));
+    }
+
+    #[test]
+    fn bollinger_entry_below_lower_band_and_exit_at_band() {
+        let upper_band = strategy(
+            "[measurement_deviation]\nsignal_mode = \"bollinger\"\n\
+             [measurement_deviation.bollinger]\nperiod = 20\nband_width = 2\nexit_band = \"upper\""
+        );
+        let strategy = strategy("[measurement_deviation]\nsignal_mode = \"bollinger\"");
+        let client = client(&strategy);
+        let position = position(90_f64);
+        let banded = Indicators {
+            bollinger_bands: Some(BollingerBands {
+                upper: 110_f64,
+                middle: 100_f64,
+                lower: 90_f64,
+            }),
+            ..indicators()
+        };
+
+        let inside = context(&strategy, &client, 95_f64, banded);
+        let pierced = context(&strategy, &client, 89_f64, banded);
+        let middle = context(&strategy, &client, 100_f64, banded);
+        let without_bands = context(&strategy, &client, 89_f64, indicators());
+
+        assert!(!TradingStrategy::is_entry_signal(&inside));
+        assert!(TradingStrategy::is_entry_signal(&pierced));
+        assert!(!TradingStrategy::is_entry_signal(&without_bands));
+        assert!(!TradingStrategy::is_exit_signal(&position, &inside));
+        assert!(TradingStrategy::is_exit_signal(&position, &middle));
+
+        let middle = context(&upper_band, &client, 100_f64, banded);
+        let upper = context(&upper_band, &client, 110_f64, banded);
+
+        assert!(!TradingStrategy::is_exit_signal(&position, &middle));
+        assert!(TradingStrategy::is_exit_signal(&position, &upper));
+    }
+
+    #[test]
+    fn rsi_filter_confirms_oversold_entries_and_exits_overbought() {
+        let strategy = strategy(
+            "[measurement_deviation.rsi]\nperiod = 14\noversold = 30\noverbought = 70"
+        );
+        let client = client(&strategy);
+        let position = position(100_f64);
+        let with_rsi = |rsi| Indicators { rsi, ..indicators() };
+
+        let oversold = context(&strategy, &client, 100_f64, with_rsi(Some(25_f64)));
+        let neutral = context(&strategy, &client, 100_f64, with_rsi(Some(50_f64)));
+        let overbought = context(&strategy, &client, 100_f64, with_rsi(Some(70_f64)));
+        let warming_up = context(&strategy, &client, 100_f64, with_rsi(None));
+
+        assert!(TradingStrategy::is_entry_confirmed(&oversold));
+        assert!(!TradingStrategy::is_entry_confirmed(&neutral));
+        assert!(!TradingStrategy::is_entry_confirmed(&warming_up));
+        assert!(!TradingStrategy::is_rsi_exit(&neutral));
+        assert!(TradingStrategy::is_rsi_exit(&overbought));
+        assert!(TradingStrategy::is_exit_signal(&position, &overbought));
+    }
+
+    #[test]
+    fn entries_are_confirmed_without_rsi_filter() {
+        let strategy = strategy("");
+        let client = client(&strategy);
+        let context = context(&strategy, &client, 100_f64, Indicators {
+            rsi: Some(90_f64),
+            ..indicators()
+        });
+
+        assert!(TradingStrategy::is_entry_confirmed(&context));
+        assert!(!TradingStrategy::is_rsi_exit(&context));
+    }
+
+    #[test]
+    fn kelly_capital_from_recent_trades() {
+        let directory = tempfile::tempdir().unwrap();
+        let store = PositionStore::new(directory.path());
+        let trade = |profit| ClosedTrade {
+            position_id: Uuid::new_v4(),
+            symbol: "BTCUSDT".to_string(),
+            cost: 100_f64,
+            proceeds: 100_f64 + profit,
+            timestamp: UtcDateTime::UNIX_EPOCH,
+        };
+        let kelly = KellySizing::default();
+
+        // 12 wins of 20 and 8 losses of 10: a 60% win probability at a 2:1 payoff
+        let mut trades: Vec<ClosedTrade> = (0..12).map(|_| trade(20_f64)).collect();
+        trades.extend((0..8).map(|_| trade(-10_f64)));
+
+        store.save_trades(trades.iter().skip(1)).unwrap();
+        let mut trading_strategy = TradingStrategy::new(5)
+            .with_position_store(PositionStore::new(directory.path()));
+        trading_strategy.restore_positions().unwrap();
+
+        assert_eq!(trading_strategy.kelly_capital(&kelly), None);
+
+        store.save_trades(trades.iter()).unwrap();
+        let mut trading_strategy = TradingStrategy::new(5)
+            .with_position_store(PositionStore::new(directory.path()));
+        trading_strategy.restore_positions().unwrap();
+
+        // Half of the 0.4 Kelly criterion
+        let capital = trading_strategy.kelly_capital(&kelly).unwrap();
+        assert!((capital - 0.2).abs() < 1e-4);
+
+        let losses: Vec<ClosedTrade> = (0..20).map(|_| trade(-10_f64)).collect();
+        store.save_trades(losses.iter()).unwrap();
+        let mut trading_strategy = TradingStrategy::new(5)
+            .with_position_store(PositionStore::new(directory.path()));
+        trading_strategy.restore_positions().unwrap();
+
+        assert_eq!(trading_strategy.kelly_capital(&kelly), Some(f64::from(kelly.min_capital)));
+
+        let wins: Vec<ClosedTrade> = (0..20).map(|_| trade(20_f64)).collect();
+        store.save_trades(wins.iter()).unwrap();
+        let mut trading_strategy = TradingStrategy::new(5)
+            .with_position_store(PositionStore::new(directory.path()));
+        trading_strategy.restore_positions().unwrap();
+
+        // Without a losing trade the fixed sizing is kept
+        assert_eq!(trading_strategy.kelly_capital(&kelly), None);
+    }
 }
@@ -1,16 +1,24 @@
+use std::{ env, path::Path };
+
 use logger::init_logger;
 use tracing::info;
 use dotenv::dotenv;
 
 use strategy::strategy::Strategy;
 use api::error::ApiError;
-use core::bot::Bot;
+use core::bot::{ Bot, RESET_BREAKER_COMMAND };
 
 pub mod api;
+pub mod backtest;
 pub mod logger;
 pub mod strategy;
 pub mod core;
 
+#[cfg(test)]
+mod tests;
+
+const BACKTEST_COMMAND: &str = "backtest";
+
 #[tokio::main]
 async fn main() -> Result<(), ApiError> {
     dotenv().unwrap();
@@ -19,7 +27,39 @@ async fn main() -> Result<(), ApiError> {
     let strategy = Strategy::new();
     info!("Loaded strategy configuration: {:?}", strategy);
 
+    let args: Vec<String> = env::args().collect();
+
+    if args.get(1).is_some_and(|command| command == BACKTEST_COMMAND) {
+        return run_backtest(strategy, &args[2..]).await;
+    }
+
+    if args.get(1).is_some_and(|command| command == RESET_BREAKER_COMMAND) {
+        return Bot::reset_circuit_breaker(&strategy);
+    }
+
     let mut bot = Bot::new(strategy);
 
     bot.run().await
 }
+
+async fn run_backtest(strategy: Strategy, args: &[String]) -> Result<(), ApiError> {
+    let candles_path = args
+        .first()
+        .ok_or(
+            ApiError::ValidationError(
+                "Usage: backtest <candles.csv|candles.json> [initial_balance]".to_string()
+            )
+        )?;
+
+    let initial_balance = args
+        .get(1)
+        .map(|balance| balance.parse::<f64>())
+        .transpose()
+        .map_err(|e| ApiError::ParseError(format!("Invalid initial balance: {e}")))?;
+
+    let report = backtest::run(strategy, Path::new(candles_path), initial_balance).await?;
+
+    println!("{report}");
+
+    Ok(())
+}
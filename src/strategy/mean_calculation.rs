@@ -2,22 +2,28 @@ use std::collections::VecDeque;
 
 use serde::{ Deserialize, Serialize };
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+use crate::core::market::ProcessedCandle;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub enum MeanCalculationMethod {
     SimpleMA,
     EMA,
-    VMAP,
+    #[serde(alias = "VMAP")]
+    VWAP,
 }
 
 pub struct MaTracker {
     method: MeanCalculationMethod,
     period: usize,
-    values: VecDeque<f64>,
+    values: VecDeque<(f64, f64)>,
     sum: f64,
+    volume_sum: f64,
+    weighted_sum: f64,
+    ema: Option<f64>,
 }
 
 pub trait MeanCalculation {
-    fn update(&mut self, value: f64) -> f64;
+    fn update(&mut self, candle: &ProcessedCandle) -> f64;
 
     fn calculate(&self) -> f64;
 }
@@ -28,22 +34,55 @@ impl MaTracker {
             method: ma_calculation_method,
             period,
             sum: 0_f64,
+            volume_sum: 0_f64,
+            weighted_sum: 0_f64,
+            ema: None,
             values: VecDeque::new(),
         }
     }
+
+    fn update_ema(&mut self, value: f64) {
+        let smoothing = 2_f64 / ((self.period as f64) + 1_f64);
+
+        self.ema = match self.ema {
+            Some(previous) => Some(value * smoothing + previous * (1_f64 - smoothing)),
+            // EMA is seeded with the simple average once the first full period is available
+            None if self.values.len() >= self.period => {
+                Some(self.sum / (self.values.len() as f64))
+            }
+            None => None,
+        };
+    }
+
+    fn simple_average(&self) -> f64 {
+        self.sum / (self.values.len() as f64)
+    }
 }
 
 impl MeanCalculation for MaTracker {
-    fn update(&mut self, value: f64) -> f64 {
+    fn update(&mut self, candle: &ProcessedCandle) -> f64 {
+        let value = match self.method {
+            MeanCalculationMethod::VWAP => candle.typical_price(),
+            _ => candle.close,
+        };
+
         self.sum += value;
-        self.values.push_back(value);
+        self.volume_sum += candle.volume;
+        self.weighted_sum += value * candle.volume;
+        self.values.push_back((value, candle.volume));
 
         if self.values.len() > self.period {
-            if let Some(outdated) = self.values.pop_front() {
+            if let Some((outdated, outdated_volume)) = self.values.pop_front() {
                 self.sum -= outdated;
+                self.volume_sum -= outdated_volume;
+                self.weighted_sum -= outdated * outdated_volume;
             }
         }
 
+        if self.method == MeanCalculationMethod::EMA {
+            self.update_ema(value);
+        }
+
         self.calculate()
     }
 
@@ -53,9 +92,91 @@ impl MeanCalculation for MaTracker {
         }
 
         match &self.method {
-            MeanCalculationMethod::SimpleMA => { self.sum / (self.values.len() as f64) }
-            MeanCalculationMethod::EMA => todo!(),
-            MeanCalculationMethod::VMAP => todo!(),
+            MeanCalculationMethod::SimpleMA => self.simple_average(),
+            MeanCalculationMethod::EMA => self.ema.unwrap_or_else(|| self.simple_average()),
+            MeanCalculationMethod::VWAP => {
+                if self.volume_sum > 0_f64 {
+                    self.weighted_sum / self.volume_sum
+                } else {
+                    self.simple_average()
+                }
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(close: f64, volume: f64) -> ProcessedCandle {
+        ProcessedCandle { timestamp: 0, open: close, high: close, low: close, close, volume }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {expected}, got {actual}");
+    }
+
+    #[test]
+    fn simple_moving_average() {
+        let mut tracker = MaTracker::new(3, MeanCalculationMethod::SimpleMA);
+
+        assert_eq!(tracker.calculate(), 0_f64);
+        assert_close(tracker.update(&candle(1_f64, 1_f64)), 1_f64);
+        assert_close(tracker.update(&candle(2_f64, 1_f64)), 1.5);
+        assert_close(tracker.update(&candle(3_f64, 1_f64)), 2_f64);
+        assert_close(tracker.update(&candle(7_f64, 1_f64)), 4_f64);
+    }
+
+    #[test]
+    fn exponential_moving_average() {
+        let mut tracker = MaTracker::new(3, MeanCalculationMethod::EMA);
+
+        assert_close(tracker.update(&candle(2_f64, 1_f64)), 2_f64);
+        assert_close(tracker.update(&candle(4_f64, 1_f64)), 3_f64);
+        // seeded with SMA(2, 4, 6) = 4, smoothing = 0.5
+        assert_close(tracker.update(&candle(6_f64, 1_f64)), 4_f64);
+        assert_close(tracker.update(&candle(8_f64, 1_f64)), 6_f64);
+        assert_close(tracker.update(&candle(2_f64, 1_f64)), 4_f64);
+    }
+
+    #[test]
+    fn volume_weighted_average() {
+        let mut tracker = MaTracker::new(2, MeanCalculationMethod::VWAP);
+
+        assert_close(tracker.update(&candle(10_f64, 1_f64)), 10_f64);
+        assert_close(tracker.update(&candle(20_f64, 3_f64)), 17.5);
+        assert_close(tracker.update(&candle(30_f64, 1_f64)), 22.5);
+
+        let mut typical_price_tracker = MaTracker::new(2, MeanCalculationMethod::VWAP);
+        let candle = ProcessedCandle {
+            timestamp: 0,
+            open: 10_f64,
+            high: 12_f64,
+            low: 6_f64,
+            close: 9_f64,
+            volume: 2_f64,
+        };
+
+        assert_close(typical_price_tracker.update(&candle), 9_f64);
+    }
+
+    #[test]
+    fn parse_mean_calculation_method() {
+        let method: MeanCalculationMethod = serde_json::from_str("\"VWAP\"").unwrap();
+        let legacy_method: MeanCalculationMethod = serde_json::from_str("\"VMAP\"").unwrap();
+
+        assert_eq!(method, MeanCalculationMethod::VWAP);
+        assert_eq!(legacy_method, MeanCalculationMethod::VWAP);
+        assert!(serde_json::from_str::<MeanCalculationMethod>("\"WMA\"").is_err());
+    }
+
+    #[test]
+    fn volume_weighted_average_without_volume() {
+        let mut tracker = MaTracker::new(2, MeanCalculationMethod::VWAP);
+
+        tracker.update(&candle(10_f64, 0_f64));
+
+        assert_close(tracker.update(&candle(20_f64, 0_f64)), 15_f64);
+    }
+}
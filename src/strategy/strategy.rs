@@ -1,15 +1,29 @@
-use std::fmt::Debug;
+use std::{
+    collections::{ HashMap, HashSet },
+    fmt::Debug,
+    path::{ Path, PathBuf },
+    time::Duration,
+};
 
 use config::{ Config, File };
 use serde::{ Deserialize, Serialize };
-use tracing::{ error, trace };
+use tracing::{ error, info, trace };
 
-use crate::api::supported_api::Api;
+use crate::{
+    api::{
+        client::ApiClient,
+        paper::PaperApi,
+        supported_api::{ Api, ExchangeEnvironment, MarketDataSource, TradingMode },
+    },
+    core::position_store::PositionStore,
+};
 use super::timeframe::StrategyTimeframe;
 
 const CONFIG_FILE_PATH: &str = "strategy.toml";
+const PAPER_STATE_DIRECTORY: &str = "paper";
+const TESTNET_STATE_DIRECTORY: &str = "testnet";
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Strategy {
     pub symbol: String,
 
@@ -24,25 +38,250 @@ pub struct Strategy {
     pub risk_management: RiskManagement,
 
     pub measurement_deviation: MeasurementDeviation,
+
+    pub regime_filter: Option<RegimeFilter>,
+
+    #[serde(default)]
+    pub storage: Storage,
+
+    #[serde(default)]
+    pub markets: Vec<MarketOverride>,
+
+    #[serde(default)]
+    pub pairs: Vec<PairStrategy>,
+}
+
+/// Two symbols traded against each other on the z-score of their spread
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PairStrategy {
+    pub first_symbol: String,
+
+    pub second_symbol: String,
+
+    /// Number of candles the hedge ratio and the spread statistics are estimated from
+    pub lookback: usize,
+
+    pub entry_z_score: f32,
+
+    pub exit_z_score: f32,
+
+    pub capital_per_trade: f32,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl PairStrategy {
+    pub fn name(&self) -> String {
+        format!("{}/{}", self.first_symbol, self.second_symbol)
+    }
+}
+
+/// Additional symbol traded by the same bot, sections left out fall back to the top-level ones
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MarketOverride {
+    pub symbol: String,
+
+    pub pair: String,
+
+    pub timeframe: Option<StrategyTimeframe>,
+
+    pub risk_management: Option<RiskManagement>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Exchange {
     pub api: Api,
+
+    #[serde(default)]
+    pub environment: ExchangeEnvironment,
+
+    #[serde(default)]
+    pub mode: TradingMode,
+
+    #[serde(default)]
+    pub market_data: MarketDataSource,
+
+    #[serde(default)]
+    pub paper: PaperTrading,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PaperTrading {
+    pub initial_balance: f64,
+    pub fee: f32,
+    pub slippage: f32,
+    /// Initial base asset holdings per traded symbol
+    #[serde(default)]
+    pub holdings: HashMap<String, f64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Storage {
+    pub data_directory: PathBuf,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct RiskManagement {
     pub capital_per_trade: f32,
     pub max_positions: usize,
     pub max_drawdown: f32,
     pub stop_loss: f32,
     pub profit_level: f32,
+
+    #[serde(default)]
+    pub profit_exit: ProfitExit,
+
+    /// Closes every open position once the drawdown circuit breaker halts trading
+    #[serde(default)]
+    pub liquidate_on_halt: bool,
+
+    #[serde(default)]
+    pub position_sizing: PositionSizing,
+
+    #[serde(default)]
+    pub atr: AtrSizing,
+
+    #[serde(default)]
+    pub kelly: KellySizing,
+
+    pub trailing_stop: Option<TrailingStop>,
+
+    pub max_holding_period: Option<HoldingPeriod>,
+}
+
+/// Time a position may stay open, either a number of bars or a duration such as `"2d"`
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy)]
+#[serde(untagged)]
+pub enum HoldingPeriod {
+    Bars(u32),
+    Duration(#[serde(with = "humantime_serde")] Duration),
+}
+
+impl HoldingPeriod {
+    pub fn duration(&self, bar: Duration) -> Duration {
+        match self {
+            Self::Bars(bars) => bar * *bars,
+            Self::Duration(duration) => *duration,
+        }
+    }
+}
+
+/// Stop following the highest price observed since a position was opened
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct TrailingStop {
+    pub mode: TrailingStopMode,
+    /// Distance below the peak in percent, used by the `percentage` mode
+    pub distance: f32,
+    /// Distance below the peak in multiples of the ATR, used by the `atr` mode
+    pub atr_multiplier: f32,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TrailingStopMode {
+    #[default]
+    Percentage,
+    Atr,
+}
+
+/// How positions of the `deviation` signal mode take their profit
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfitExit {
+    /// Closes every position once the moving average deviation reaches `profit_level`
+    #[default]
+    Deviation,
+    /// Closes a position once it gained `profit_level` percent over its entry price
+    TakeProfit,
+    /// Closes a position once the price crosses above the long moving average
+    Mean,
+}
+
+/// How the quantity of a new position is calculated
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PositionSizing {
+    /// Spends `capital_per_trade` of the balance
+    #[default]
+    Fixed,
+    /// Risks `atr.risk_per_trade` of the balance on a stop `atr.stop_multiplier` ATRs away
+    Atr,
+    /// Spends a fraction of the Kelly criterion estimated from the closed trades
+    Kelly,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AtrSizing {
+    pub period: usize,
+    pub stop_multiplier: f32,
+    pub risk_per_trade: f32,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct MeasurementDeviation {
+    #[serde(default)]
+    pub signal_mode: SignalMode,
+
     pub enter_deviation: f32,
+
+    #[serde(default)]
+    pub zscore: ZScoreSignal,
+
+    #[serde(default)]
+    pub bollinger: BollingerSignal,
+
+    pub rsi: Option<RsiFilter>,
+}
+
+/// Signal used to enter positions and to exit them once the price reverted
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SignalMode {
+    /// Short/long moving average gap in percent against `enter_deviation`
+    #[default]
+    Deviation,
+    /// Z-score of the price against the rolling mean and standard deviation of `measure_bars`
+    ZScore,
+    /// Close piercing the lower Bollinger Band
+    Bollinger,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ZScoreSignal {
+    pub entry_threshold: f32,
+    pub exit_threshold: f32,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BollingerSignal {
+    pub period: usize,
+    pub band_width: f32,
+    pub exit_band: BollingerExitBand,
+}
+
+/// Confirms entries with the RSI being oversold, optionally closing positions once overbought
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RsiFilter {
+    pub period: usize,
+    pub oversold: f32,
+    pub overbought: Option<f32>,
+}
+
+/// Suspends new entries while the last `lookback` closes trend instead of reverting to the mean
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct RegimeFilter {
+    pub lookback: usize,
+    pub max_hurst: f32,
+    pub adf_critical_value: f32,
+}
+
+/// Band the close has to reach for a position to be closed
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BollingerExitBand {
+    #[default]
+    Middle,
+    Upper,
 }
 
 impl Strategy {
@@ -63,13 +302,191 @@ impl Strategy {
             })
             .unwrap();
 
+        let markets = strategy.markets();
+        let unique_symbols: HashSet<&str> = markets
+            .iter()
+            .map(|market| market.symbol.as_str())
+            .collect();
+
         assert!(
-            strategy.symbol != strategy.trading_symbol,
+            markets.iter().all(|market| market.symbol != strategy.trading_symbol),
             "Invalid strategy configuration: symbol and trading_symbol cannot be the same"
         );
+        assert!(
+            unique_symbols.len() == markets.len(),
+            "Invalid strategy configuration: every traded symbol must be unique"
+        );
+        assert!(
+            strategy.pairs.iter().all(|pair| pair.first_symbol != pair.second_symbol),
+            "Invalid strategy configuration: pair symbols cannot be the same"
+        );
+        assert!(
+            strategy.pairs.iter().all(|pair| pair.entry_z_score > pair.exit_z_score),
+            "Invalid strategy configuration: pair entry_z_score must be above exit_z_score"
+        );
+        assert!(
+            markets.iter().all(|market| {
+                let kelly = &market.risk_management.kelly;
+                kelly.min_capital <= kelly.max_capital && kelly.min_trades <= kelly.window
+            }),
+            "Invalid strategy configuration: kelly min_capital must not exceed max_capital \
+             and min_trades must not exceed window"
+        );
 
         strategy
     }
+
+    /// Asset bought and sold by the strategy, e.g. `BTC` for `BTCUSDT` traded with `USDT`
+    pub fn base_asset(&self) -> &str {
+        self.base_asset_of(&self.symbol)
+    }
+
+    /// Base asset of any `symbol` quoted in `trading_symbol`
+    pub fn base_asset_of<'a>(&self, symbol: &'a str) -> &'a str {
+        symbol.strip_suffix(&self.trading_symbol).unwrap_or(symbol)
+    }
+
+    /// Resolves the strategy of every traded symbol, the top-level symbol first followed by
+    /// `markets` with their overrides applied
+    pub fn markets(&self) -> Vec<Strategy> {
+        let base = Strategy { markets: Vec::new(), pairs: Vec::new(), ..self.clone() };
+
+        let overrides = self.markets.iter().map(|market| Strategy {
+            symbol: market.symbol.clone(),
+            pair: market.pair.clone(),
+            timeframe: market.timeframe.clone().unwrap_or_else(|| base.timeframe.clone()),
+            risk_management: market.risk_management
+                .clone()
+                .unwrap_or_else(|| base.risk_management.clone()),
+            ..base.clone()
+        });
+
+        std::iter::once(base.clone()).chain(overrides).collect()
+    }
+
+    /// Directory the state of the exchange mode is kept in, paper and testnet runs are kept
+    /// apart from the mainnet state in the root of `data_directory`
+    pub fn state_directory(&self) -> PathBuf {
+        let data_directory = &self.storage.data_directory;
+
+        match (self.exchange.mode, self.exchange.environment) {
+            (TradingMode::Paper, _) => data_directory.join(PAPER_STATE_DIRECTORY),
+            (TradingMode::Live, ExchangeEnvironment::Testnet) =>
+                data_directory.join(TESTNET_STATE_DIRECTORY),
+            (TradingMode::Live, ExchangeEnvironment::Mainnet) => data_directory.clone(),
+        }
+    }
+}
+
+impl Exchange {
+    /// Client of the configured api, paper trading keeps its ledger in `state_directory`
+    pub fn get_client(&self, quote_asset: &str, state_directory: &Path) -> Box<dyn ApiClient> {
+        let environment = format!("{:?} {:?}", self.api, self.environment).to_uppercase();
+
+        info!("Connecting to {} in {:?} mode", environment, self.mode);
+
+        match self.mode {
+            TradingMode::Live => Box::new(self.api.get_client(self.environment)),
+            TradingMode::Paper => {
+                let market_data = Box::new(self.api.get_market_data_client(self.environment));
+
+                Box::new(
+                    PaperApi::new(market_data, quote_asset, &self.paper).with_store(
+                        PositionStore::new(state_directory)
+                    )
+                )
+            }
+        }
+    }
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Self {
+            data_directory: PathBuf::from("data"),
+        }
+    }
+}
+
+impl Default for ZScoreSignal {
+    fn default() -> Self {
+        Self {
+            entry_threshold: 2.0,
+            exit_threshold: 0.0,
+        }
+    }
+}
+
+impl Default for BollingerSignal {
+    fn default() -> Self {
+        Self {
+            period: 20,
+            band_width: 2.0,
+            exit_band: BollingerExitBand::Middle,
+        }
+    }
+}
+
+impl Default for RegimeFilter {
+    fn default() -> Self {
+        Self {
+            lookback: 100,
+            max_hurst: 0.5,
+            adf_critical_value: -2.86,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct KellySizing {
+    pub window: usize,
+    pub min_trades: usize,
+    pub fraction: f32,
+    pub min_capital: f32,
+    pub max_capital: f32,
+}
+
+impl Default for KellySizing {
+    fn default() -> Self {
+        Self {
+            window: 50,
+            min_trades: 20,
+            fraction: 0.5,
+            min_capital: 0.01,
+            max_capital: 0.25,
+        }
+    }
+}
+
+impl Default for TrailingStop {
+    fn default() -> Self {
+        Self {
+            mode: TrailingStopMode::Percentage,
+            distance: 1.0,
+            atr_multiplier: 3.0,
+        }
+    }
+}
+
+impl Default for AtrSizing {
+    fn default() -> Self {
+        Self {
+            period: 14,
+            stop_multiplier: 2.0,
+            risk_per_trade: 0.01,
+        }
+    }
+}
+
+impl Default for PaperTrading {
+    fn default() -> Self {
+        Self {
+            initial_balance: 1000_f64,
+            fee: 0.1,
+            slippage: 0.05,
+            holdings: HashMap::new(),
+        }
+    }
 }
 
 impl Default for Strategy {
@@ -133,6 +550,7 @@ enter_deviation = 0.1
 
         assert_eq!(strategy.symbol, "BTCUSDT");
         assert_eq!(strategy.pair, "BTC/USDT");
+        assert_eq!(strategy.base_asset(), "BTC");
 
         assert_eq!(
             duration_from_kline_interval(&strategy.timeframe.interval),
@@ -141,6 +559,10 @@ enter_deviation = 0.1
         assert_eq!(strategy.timeframe.tick, Duration::from_secs(60 * 15));
 
         assert_eq!(strategy.exchange.api, Api::Binance);
+        assert_eq!(strategy.exchange.environment, ExchangeEnvironment::Mainnet);
+        assert_eq!(strategy.exchange.mode, TradingMode::Live);
+        assert_eq!(strategy.exchange.market_data, MarketDataSource::Stream);
+        assert_eq!(strategy.storage.data_directory, PathBuf::from("data"));
 
         assert_eq!(strategy.risk_management.capital_per_trade, 0.1);
         assert_eq!(strategy.risk_management.max_positions, 5);
@@ -149,6 +571,17 @@ enter_deviation = 0.1
         assert_eq!(strategy.risk_management.profit_level, 0.15);
 
         assert_eq!(strategy.measurement_deviation.enter_deviation, 0.1);
+        assert_eq!(strategy.measurement_deviation.signal_mode, SignalMode::Deviation);
+        assert_eq!(strategy.measurement_deviation.zscore.entry_threshold, 2.0);
+        assert!(strategy.measurement_deviation.rsi.is_none());
+        assert!(strategy.regime_filter.is_none());
+        assert_eq!(strategy.risk_management.position_sizing, PositionSizing::Fixed);
+        assert_eq!(strategy.risk_management.atr.period, 14);
+        assert_eq!(strategy.risk_management.kelly.window, 50);
+        assert!(!strategy.risk_management.liquidate_on_halt);
+        assert_eq!(strategy.risk_management.profit_exit, ProfitExit::Deviation);
+        assert!(strategy.risk_management.trailing_stop.is_none());
+        assert!(strategy.risk_management.max_holding_period.is_none());
     }
 
     #[test]
@@ -210,10 +643,410 @@ enter_deviation = 0.01
         assert!(result.is_err());
     }
 
+    #[test]
+    fn parse_paper_trading_mode() {
+        let paper_toml_config =
+            r#"
+symbol = "BTCUSDT"
+pair = "BTC/USDT"
+trading_symbol = "USDT"
+
+[timeframe]
+interval = "1h"
+tick = "15m"
+
+[timeframe.period_measurement]
+measure_bars = 20
+mean_calculation_method="SimpleMA"
+
+[exchange]
+api = "binance"
+environment = "testnet"
+mode = "paper"
+market_data = "polling"
+
+[exchange.paper]
+initial_balance = 500
+fee = 0.075
+slippage = 0.02
+
+[risk_management]
+capital_per_trade = 0.1
+max_positions = 5
+max_drawdown = 0.5
+stop_loss = 0.3
+profit_level = 0.15
+
+[measurement_deviation]
+enter_deviation = 0.1
+            "#;
+        let temp_config_file = create_tmp_test_config(paper_toml_config);
+        let path = temp_config_file.path().to_str().unwrap();
+
+        let config = Config::builder().add_source(File::with_name(path)).build().unwrap();
+
+        let strategy = config.try_deserialize::<Strategy>().unwrap();
+
+        assert_eq!(strategy.exchange.environment, ExchangeEnvironment::Testnet);
+        assert_eq!(strategy.exchange.mode, TradingMode::Paper);
+        assert_eq!(strategy.exchange.market_data, MarketDataSource::Polling);
+        assert_eq!(strategy.exchange.paper.initial_balance, 500_f64);
+        assert_eq!(strategy.exchange.paper.fee, 0.075);
+        assert_eq!(strategy.exchange.paper.slippage, 0.02);
+    }
+
+    #[test]
+    fn parse_zscore_signal_mode() {
+        let zscore_toml_config =
+            r#"
+symbol = "BTCUSDT"
+pair = "BTC/USDT"
+trading_symbol = "USDT"
+
+[timeframe]
+interval = "1h"
+tick = "15m"
+
+[timeframe.period_measurement]
+measure_bars = 20
+mean_calculation_method="SimpleMA"
+
+[exchange]
+api = "binance"
+
+[risk_management]
+capital_per_trade = 0.1
+max_positions = 5
+max_drawdown = 0.5
+stop_loss = 0.3
+profit_level = 0.15
+
+[measurement_deviation]
+signal_mode = "zscore"
+enter_deviation = 0.1
+
+[measurement_deviation.zscore]
+entry_threshold = 2.5
+exit_threshold = 0.5
+            "#;
+        let temp_config_file = create_tmp_test_config(zscore_toml_config);
+        let path = temp_config_file.path().to_str().unwrap();
+
+        let config = Config::builder().add_source(File::with_name(path)).build().unwrap();
+
+        let strategy = config.try_deserialize::<Strategy>().unwrap();
+
+        assert_eq!(strategy.measurement_deviation.signal_mode, SignalMode::ZScore);
+        assert_eq!(strategy.measurement_deviation.zscore.entry_threshold, 2.5);
+        assert_eq!(strategy.measurement_deviation.zscore.exit_threshold, 0.5);
+    }
+
+    #[test]
+    fn parse_bollinger_signal_mode() {
+        let bollinger_toml_config =
+            r#"
+symbol = "BTCUSDT"
+pair = "BTC/USDT"
+trading_symbol = "USDT"
+
+[timeframe]
+interval = "1h"
+tick = "15m"
+
+[timeframe.period_measurement]
+measure_bars = 20
+mean_calculation_method="SimpleMA"
+
+[exchange]
+api = "binance"
+
+[risk_management]
+capital_per_trade = 0.1
+max_positions = 5
+max_drawdown = 0.5
+stop_loss = 0.3
+profit_level = 0.15
+
+[measurement_deviation]
+signal_mode = "bollinger"
+enter_deviation = 0.1
+
+[measurement_deviation.bollinger]
+period = 30
+band_width = 2.5
+exit_band = "upper"
+            "#;
+        let temp_config_file = create_tmp_test_config(bollinger_toml_config);
+        let path = temp_config_file.path().to_str().unwrap();
+
+        let config = Config::builder().add_source(File::with_name(path)).build().unwrap();
+
+        let strategy = config.try_deserialize::<Strategy>().unwrap();
+        let bollinger = &strategy.measurement_deviation.bollinger;
+
+        assert_eq!(strategy.measurement_deviation.signal_mode, SignalMode::Bollinger);
+        assert_eq!(bollinger.period, 30);
+        assert_eq!(bollinger.band_width, 2.5);
+        assert_eq!(bollinger.exit_band, BollingerExitBand::Upper);
+    }
+
+    #[test]
+    fn parse_rsi_and_regime_filters() {
+        let rsi_toml_config =
+            r#"
+symbol = "BTCUSDT"
+pair = "BTC/USDT"
+trading_symbol = "USDT"
+
+[timeframe]
+interval = "1h"
+tick = "15m"
+
+[timeframe.period_measurement]
+measure_bars = 20
+mean_calculation_method="SimpleMA"
+
+[exchange]
+api = "binance"
+
+[risk_management]
+capital_per_trade = 0.1
+max_positions = 5
+max_drawdown = 0.5
+stop_loss = 0.3
+profit_level = 0.15
+
+[measurement_deviation]
+enter_deviation = 0.1
+
+[measurement_deviation.rsi]
+period = 14
+oversold = 30
+
+[regime_filter]
+lookback = 200
+            "#;
+        let temp_config_file = create_tmp_test_config(rsi_toml_config);
+        let path = temp_config_file.path().to_str().unwrap();
+
+        let config = Config::builder().add_source(File::with_name(path)).build().unwrap();
+
+        let strategy = config.try_deserialize::<Strategy>().unwrap();
+        let rsi = strategy.measurement_deviation.rsi.unwrap();
+
+        assert_eq!(rsi.period, 14);
+        assert_eq!(rsi.oversold, 30.0);
+        assert!(rsi.overbought.is_none());
+
+        let regime_filter = strategy.regime_filter.unwrap();
+
+        assert_eq!(regime_filter.lookback, 200);
+        assert_eq!(regime_filter.max_hurst, 0.5);
+        assert_eq!(regime_filter.adf_critical_value, -2.86);
+    }
+
+    #[test]
+    fn parse_max_holding_period_in_bars() {
+        let holding_period_toml_config =
+            r#"
+symbol = "BTCUSDT"
+pair = "BTC/USDT"
+trading_symbol = "USDT"
+
+[timeframe]
+interval = "1h"
+tick = "15m"
+
+[timeframe.period_measurement]
+measure_bars = 20
+mean_calculation_method="SimpleMA"
+
+[exchange]
+api = "binance"
+
+[risk_management]
+capital_per_trade = 0.1
+max_positions = 5
+max_drawdown = 0.5
+stop_loss = 0.3
+profit_level = 0.15
+max_holding_period = 48
+
+[measurement_deviation]
+enter_deviation = 0.1
+            "#;
+        let temp_config_file = create_tmp_test_config(holding_period_toml_config);
+        let path = temp_config_file.path().to_str().unwrap();
+
+        let config = Config::builder().add_source(File::with_name(path)).build().unwrap();
+
+        let strategy = config.try_deserialize::<Strategy>().unwrap();
+        let max_holding_period = strategy.risk_management.max_holding_period.unwrap();
+
+        assert_eq!(max_holding_period, HoldingPeriod::Bars(48));
+        assert_eq!(
+            max_holding_period.duration(strategy.timeframe.tick),
+            Duration::from_secs(12 * 60 * 60)
+        );
+    }
+
+    #[test]
+    fn resolve_market_overrides() {
+        let multi_symbol_toml_config =
+            r#"
+symbol = "BTCUSDT"
+pair = "BTC/USDT"
+trading_symbol = "USDT"
+
+[timeframe]
+interval = "1h"
+tick = "15m"
+
+[timeframe.period_measurement]
+measure_bars = 20
+mean_calculation_method="SimpleMA"
+
+[exchange]
+api = "binance"
+
+[risk_management]
+capital_per_trade = 0.1
+max_positions = 5
+max_drawdown = 0.5
+stop_loss = 0.3
+profit_level = 0.15
+
+[measurement_deviation]
+enter_deviation = 0.1
+
+[[markets]]
+symbol = "ETHUSDT"
+pair = "ETH/USDT"
+
+[markets.timeframe]
+interval = "4h"
+tick = "1h"
+
+[markets.timeframe.period_measurement]
+measure_bars = 30
+mean_calculation_method="EMA"
+
+[[markets]]
+symbol = "SOLUSDT"
+pair = "SOL/USDT"
+
+[[pairs]]
+first_symbol = "BTCUSDT"
+second_symbol = "ETHUSDT"
+lookback = 100
+entry_z_score = 2.0
+exit_z_score = 0.5
+capital_per_trade = 0.1
+
+[markets.risk_management]
+capital_per_trade = 0.05
+max_positions = 2
+max_drawdown = 0.5
+stop_loss = 1.0
+profit_level = 0.3
+position_sizing = "atr"
+profit_exit = "mean"
+max_holding_period = "2d"
+
+[markets.risk_management.atr]
+period = 10
+stop_multiplier = 3.0
+risk_per_trade = 0.02
+
+[markets.risk_management.trailing_stop]
+mode = "atr"
+
+[markets.risk_management.kelly]
+window = 30
+min_trades = 10
+fraction = 0.25
+min_capital = 0.02
+max_capital = 0.2
+            "#;
+        let temp_config_file = create_tmp_test_config(multi_symbol_toml_config);
+        let path = temp_config_file.path().to_str().unwrap();
+
+        let config = Config::builder().add_source(File::with_name(path)).build().unwrap();
+
+        let strategy = config.try_deserialize::<Strategy>().unwrap();
+        let markets = strategy.markets();
+
+        assert_eq!(markets.len(), 3);
+        assert!(markets.iter().all(|market| market.markets.is_empty()));
+        assert!(markets.iter().all(|market| market.pairs.is_empty()));
+
+        assert_eq!(markets[0].symbol, "BTCUSDT");
+        assert_eq!(markets[0].timeframe.tick, Duration::from_secs(60 * 15));
+
+        assert_eq!(markets[1].symbol, "ETHUSDT");
+        assert_eq!(markets[1].base_asset(), "ETH");
+        assert_eq!(markets[1].timeframe.tick, Duration::from_secs(60 * 60));
+        assert_eq!(markets[1].timeframe.period_measurement.measure_bars, 30);
+        assert_eq!(markets[1].risk_management.max_positions, 5);
+
+        assert_eq!(markets[2].symbol, "SOLUSDT");
+        assert_eq!(markets[2].timeframe.tick, Duration::from_secs(60 * 15));
+        assert_eq!(markets[2].risk_management.max_positions, 2);
+        assert_eq!(markets[2].risk_management.stop_loss, 1.0);
+        assert_eq!(markets[2].risk_management.position_sizing, PositionSizing::Atr);
+        assert_eq!(markets[2].risk_management.profit_exit, ProfitExit::Mean);
+        assert_eq!(
+            markets[2].risk_management.max_holding_period,
+            Some(HoldingPeriod::Duration(Duration::from_secs(2 * 24 * 60 * 60)))
+        );
+        assert_eq!(markets[2].risk_management.atr.stop_multiplier, 3.0);
+        assert_eq!(markets[2].risk_management.kelly.fraction, 0.25);
+
+        let trailing_stop = markets[2].risk_management.trailing_stop.as_ref().unwrap();
+
+        assert_eq!(trailing_stop.mode, TrailingStopMode::Atr);
+        assert_eq!(trailing_stop.atr_multiplier, 3.0);
+        assert_eq!(markets[1].risk_management.kelly.window, 50);
+        assert_eq!(markets[1].risk_management.position_sizing, PositionSizing::Fixed);
+
+        assert_eq!(strategy.pairs.len(), 1);
+        assert_eq!(strategy.pairs[0].name(), "BTCUSDT/ETHUSDT");
+        assert_eq!(strategy.pairs[0].lookback, 100);
+        assert_eq!(strategy.pairs[0].exit_z_score, 0.5);
+    }
+
     #[test]
     fn test_nonexistent_file() {
         let config = Config::builder().add_source(File::with_name("nonexistent_file.toml")).build();
 
         assert!(config.is_err());
     }
+
+    #[test]
+    fn keeps_state_of_each_exchange_mode_apart() {
+        let state_directory = |exchange: &str| {
+            crate::tests::strategy::strategy(&format!("[exchange]\n{exchange}")).state_directory()
+        };
+
+        assert_eq!(state_directory(""), PathBuf::from("data"));
+        assert_eq!(state_directory("environment = \"testnet\""), PathBuf::from("data/testnet"));
+        assert_eq!(state_directory("mode = \"paper\""), PathBuf::from("data/paper"));
+        assert_eq!(
+            state_directory("mode = \"paper\"\nenvironment = \"testnet\""),
+            PathBuf::from("data/paper")
+        );
+    }
+
+    #[tokio::test]
+    async fn paper_client_reads_no_credentials() {
+        let strategy = crate::tests::strategy::strategy(
+            "[exchange]\nmode = \"paper\"\nenvironment = \"testnet\""
+        );
+        let directory = tempfile::tempdir().unwrap();
+
+        // Building the client does not look up the testnet API key and secret
+        let client = strategy.exchange.get_client(&strategy.trading_symbol, directory.path());
+
+        assert_eq!(client.get_account_balance("USDT").await.unwrap(), 1000_f64);
+    }
 }
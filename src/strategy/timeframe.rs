@@ -6,7 +6,7 @@ use serde::{ de::Visitor, Deserialize, Deserializer, Serialize, Serializer };
 use crate::api::error::ApiError;
 use super::mean_calculation::MeanCalculationMethod;
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone)]
 pub struct StrategyTimeframe {
     #[serde(
         deserialize_with = "deserialize_kline_interval",
@@ -19,7 +19,7 @@ pub struct StrategyTimeframe {
     pub period_measurement: PeriodMeasurement,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PeriodMeasurement {
     pub measure_bars: usize,
     pub mean_calculation_method: MeanCalculationMethod,
@@ -0,0 +1,367 @@
+use std::{ path::Path, time::{ Duration, Instant } };
+
+use hyper::StatusCode;
+
+use crate::{ api::paper::PaperApi, core::{ bot::Bot, position_store::PositionStore } };
+use super::{
+    mock_binance::{
+        klines,
+        ticker_price,
+        MockBinance,
+        MockResponse,
+        ACCOUNT_PATH,
+        KLINES_PATH,
+        ORDER_PATH,
+        TICKER_PRICE_PATH,
+    },
+    strategy::strategy,
+};
+
+const HOUR: u64 = 60 * 60;
+/// Open time of the candle forming when the bot starts
+const START: u64 = 1_700_000_000 / HOUR * HOUR;
+const HISTORY_SIZE: usize = 60;
+
+/// Serves a flat history of closed candles followed by the still forming one
+fn serve_history(mock: &MockBinance) {
+    let history_start = START - (HISTORY_SIZE as u64) * HOUR;
+    let mut closes = vec![100_f64; HISTORY_SIZE];
+    closes.push(50_f64);
+
+    mock.set_server_time((START + HOUR / 2) * 1000);
+    mock.enqueue(KLINES_PATH, StatusCode::OK, &klines(history_start, HOUR, &closes));
+}
+
+async fn initialized_bot(mock: &MockBinance) -> Bot {
+    serve_history(mock);
+
+    let mut bot = Bot::with_client(strategy(""), Box::new(mock.client()));
+
+    bot.initialize().await.unwrap();
+
+    bot
+}
+
+/// Moves the server clock into candle `index` after the start, closing the previous one at
+/// `close`
+fn close_candle(mock: &MockBinance, index: u64, close: f64) {
+    let open_time = START + (index - 1) * HOUR;
+
+    mock.set_server_time((open_time + HOUR + HOUR / 2) * 1000);
+    mock.enqueue(KLINES_PATH, StatusCode::OK, &klines(open_time, HOUR, &[close, close]));
+}
+
+fn order_sides(mock: &MockBinance) -> Vec<String> {
+    mock.requests(ORDER_PATH)
+        .iter()
+        .filter(|request| request.method == "POST")
+        .filter_map(|request| {
+            request.query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("side="))
+                .map(str::to_string)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn initializes_from_closed_candles_and_balance() {
+    let mock = MockBinance::start().await;
+    let bot = initialized_bot(&mock).await;
+
+    assert_eq!(bot.account_balance(), 1000_f64);
+    assert_eq!(bot.open_positions_count(), 0);
+    assert_eq!(mock.requests(KLINES_PATH).len(), 1);
+}
+
+#[tokio::test]
+async fn trades_on_paths_served_by_the_testnet() {
+    let mock = MockBinance::start().await;
+    let mut bot = initialized_bot(&mock).await;
+
+    close_candle(&mock, 1, 90_f64);
+    bot.poll_closed_candles().await.unwrap();
+
+    assert_eq!(order_sides(&mock), ["BUY"]);
+    // The testnet only serves the spot API, none of the wallet /sapi endpoints
+    assert!(mock.paths().iter().all(|path| path.starts_with("/api/v3/")));
+    assert_eq!(mock.requests(ACCOUNT_PATH).len(), 1);
+}
+
+#[tokio::test]
+async fn skips_candles_seeded_at_startup() {
+    let mock = MockBinance::start().await;
+    let mut bot = initialized_bot(&mock).await;
+
+    bot.poll_closed_candles().await.unwrap();
+
+    assert_eq!(mock.requests(KLINES_PATH).len(), 1);
+
+    // Quarter hour ticks, started before the first tick of the forming hour closed
+    let mock = MockBinance::start().await;
+    let history_start = START - (HISTORY_SIZE as u64) * HOUR;
+
+    mock.set_server_time((START + 5 * 60) * 1000);
+    mock.enqueue(KLINES_PATH, StatusCode::OK, &klines(history_start, HOUR, &[100_f64; 61]));
+
+    let strategy = strategy("[timeframe]\ninterval = \"1h\"\ntick = \"15m\"");
+    let mut bot = Bot::with_client(strategy, Box::new(mock.client()));
+
+    bot.initialize().await.unwrap();
+    bot.poll_closed_candles().await.unwrap();
+
+    assert_eq!(mock.requests(KLINES_PATH).len(), 1);
+    assert!(order_sides(&mock).is_empty());
+}
+
+#[tokio::test]
+async fn enters_and_stops_out_a_position() {
+    let mock = MockBinance::start().await;
+    let mut bot = initialized_bot(&mock).await;
+
+    close_candle(&mock, 1, 100_f64);
+    bot.poll_closed_candles().await.unwrap();
+
+    assert!(order_sides(&mock).is_empty());
+
+    close_candle(&mock, 2, 90_f64);
+    bot.poll_closed_candles().await.unwrap();
+
+    assert_eq!(order_sides(&mock), ["BUY"]);
+    assert_eq!(bot.open_positions_count(), 1);
+    assert_eq!(bot.account_balance(), 910_f64);
+
+    close_candle(&mock, 3, 80_f64);
+    bot.poll_closed_candles().await.unwrap();
+
+    // The stop loss closes the position, and the deeper deviation enters again right away
+    assert_eq!(order_sides(&mock), ["BUY", "SELL", "BUY"]);
+    assert_eq!(bot.open_positions_count(), 1);
+}
+
+#[tokio::test]
+async fn polls_every_closed_candle_once() {
+    let mock = MockBinance::start().await;
+    let mut bot = initialized_bot(&mock).await;
+
+    close_candle(&mock, 1, 90_f64);
+    bot.poll_closed_candles().await.unwrap();
+    bot.poll_closed_candles().await.unwrap();
+
+    assert_eq!(mock.requests(KLINES_PATH).len(), 2);
+    assert_eq!(order_sides(&mock), ["BUY"]);
+}
+
+#[tokio::test]
+async fn backfills_missed_candles_without_trading_on_them() {
+    let mock = MockBinance::start().await;
+    let mut bot = initialized_bot(&mock).await;
+
+    // Polled late, candles 1 and 2 closed unnoticed and only the recovered 3 is traded on.
+    // Trading the missed candles would have entered at 90 and stopped out at 80
+    mock.set_server_time((START + 3 * HOUR + HOUR / 2) * 1000);
+    mock.enqueue(KLINES_PATH, StatusCode::OK, &klines(START + 2 * HOUR, HOUR, &[80_f64; 2]));
+    mock.enqueue(KLINES_PATH, StatusCode::OK, &klines(START, HOUR, &[90_f64, 80_f64]));
+
+    bot.poll_closed_candles().await.unwrap();
+
+    assert_eq!(mock.requests(KLINES_PATH).len(), 3);
+    assert_eq!(order_sides(&mock), ["BUY"]);
+    assert_eq!(bot.open_positions_count(), 1);
+}
+
+#[tokio::test]
+async fn recovers_after_a_failed_cycle() {
+    let mock = MockBinance::start().await;
+    let mut bot = initialized_bot(&mock).await;
+
+    mock.set_server_time((START + HOUR + HOUR / 2) * 1000);
+    mock.enqueue(
+        KLINES_PATH,
+        StatusCode::BAD_REQUEST,
+        r#"{"code":-1121,"msg":"Invalid symbol."}"#
+    );
+
+    assert!(bot.poll_closed_candles().await.is_err());
+    assert_eq!(mock.requests(KLINES_PATH).len(), 2);
+
+    close_candle(&mock, 1, 90_f64);
+    bot.poll_closed_candles().await.unwrap();
+
+    assert_eq!(order_sides(&mock), ["BUY"]);
+    assert_eq!(bot.open_positions_count(), 1);
+}
+
+#[tokio::test]
+async fn keeps_position_when_order_fails() {
+    let mock = MockBinance::start().await;
+    let mut bot = initialized_bot(&mock).await;
+
+    mock.enqueue(ORDER_PATH, StatusCode::BAD_REQUEST, r#"{"code":-2010,"msg":"Insufficient"}"#);
+
+    close_candle(&mock, 1, 90_f64);
+
+    assert!(bot.poll_closed_candles().await.is_err());
+    assert_eq!(bot.open_positions_count(), 0);
+    assert_eq!(bot.account_balance(), 1000_f64);
+
+    close_candle(&mock, 2, 85_f64);
+    bot.poll_closed_candles().await.unwrap();
+
+    assert_eq!(bot.open_positions_count(), 1);
+}
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let mock = MockBinance::start().await;
+    let mut bot = initialized_bot(&mock).await;
+
+    mock.enqueue(KLINES_PATH, StatusCode::INTERNAL_SERVER_ERROR, "");
+    mock.enqueue(KLINES_PATH, StatusCode::SERVICE_UNAVAILABLE, "");
+
+    close_candle(&mock, 1, 90_f64);
+    bot.poll_closed_candles().await.unwrap();
+
+    assert_eq!(mock.requests(KLINES_PATH).len(), 4);
+    assert_eq!(order_sides(&mock), ["BUY"]);
+}
+
+#[tokio::test]
+async fn retries_rate_limited_orders() {
+    let mock = MockBinance::start().await;
+    let mut bot = initialized_bot(&mock).await;
+
+    mock.enqueue(
+        ORDER_PATH,
+        StatusCode::TOO_MANY_REQUESTS,
+        r#"{"code":-1003,"msg":"Too many requests."}"#
+    );
+
+    close_candle(&mock, 1, 90_f64);
+    bot.poll_closed_candles().await.unwrap();
+
+    assert_eq!(order_sides(&mock), ["BUY", "BUY"]);
+    assert_eq!(bot.open_positions_count(), 1);
+}
+
+/// Rate limit rejection asking to wait `retry_after` seconds
+fn rate_limited(retry_after: u64) -> MockResponse {
+    MockResponse {
+        status: StatusCode::TOO_MANY_REQUESTS,
+        body: r#"{"code":-1003,"msg":"Too many requests."}"#.to_string(),
+        headers: vec![("Retry-After".to_string(), retry_after.to_string())],
+    }
+}
+
+#[tokio::test]
+async fn waits_for_retry_after_of_rate_limited_orders() {
+    let mock = MockBinance::start().await;
+    let mut bot = initialized_bot(&mock).await;
+
+    mock.enqueue_response(ORDER_PATH, rate_limited(1));
+
+    close_candle(&mock, 1, 90_f64);
+    let started = Instant::now();
+    bot.poll_closed_candles().await.unwrap();
+
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert_eq!(order_sides(&mock), ["BUY", "BUY"]);
+}
+
+#[tokio::test]
+async fn gives_up_on_long_retry_after() {
+    let mock = MockBinance::start().await;
+    let mut bot = initialized_bot(&mock).await;
+
+    mock.enqueue_response(ORDER_PATH, rate_limited(120));
+
+    close_candle(&mock, 1, 90_f64);
+
+    assert!(bot.poll_closed_candles().await.is_err());
+    assert_eq!(order_sides(&mock), ["BUY"]);
+    assert_eq!(bot.open_positions_count(), 0);
+}
+
+#[tokio::test]
+async fn does_not_repeat_orders_with_unknown_status() {
+    let mock = MockBinance::start().await;
+    let mut bot = initialized_bot(&mock).await;
+
+    mock.enqueue(
+        ORDER_PATH,
+        StatusCode::SERVICE_UNAVAILABLE,
+        r#"{"code":-1007,"msg":"Timeout waiting for response from backend server."}"#
+    );
+
+    close_candle(&mock, 1, 90_f64);
+
+    assert!(bot.poll_closed_candles().await.is_err());
+    assert_eq!(order_sides(&mock), ["BUY"]);
+    assert_eq!(bot.open_positions_count(), 0);
+}
+
+/// Paper trading bot keeping its state in `directory`, filling orders at the ticker price
+async fn paper_bot(mock: &MockBinance, directory: &Path) -> Bot {
+    serve_history(mock);
+
+    let strategy = strategy(
+        "[exchange]\nmode = \"paper\"\n[risk_management]\nprofit_exit = \"take_profit\""
+    );
+    let paper_api = PaperApi::new(Box::new(mock.client()), "USDT", &strategy.exchange.paper)
+        .with_store(PositionStore::new(directory));
+    let mut bot = Bot::with_client(strategy, Box::new(paper_api)).with_state_directory(directory);
+
+    bot.initialize().await.unwrap();
+
+    bot
+}
+
+#[tokio::test]
+async fn paper_positions_can_be_closed_after_a_restart() {
+    let directory = tempfile::tempdir().unwrap();
+    let mock = MockBinance::start().await;
+    mock.respond(TICKER_PRICE_PATH, &ticker_price(90_f64));
+
+    let mut bot = paper_bot(&mock, directory.path()).await;
+
+    close_candle(&mock, 1, 90_f64);
+    bot.poll_closed_candles().await.unwrap();
+
+    assert_eq!(bot.open_positions_count(), 1);
+    let balance = bot.account_balance();
+    assert!(balance < 1000_f64);
+
+    let mut restarted_bot = paper_bot(&mock, directory.path()).await;
+
+    assert_eq!(restarted_bot.open_positions_count(), 1);
+    assert_eq!(restarted_bot.account_balance(), balance);
+
+    // The take profit sells the holdings bought before the restart
+    mock.respond(TICKER_PRICE_PATH, &ticker_price(200_f64));
+    close_candle(&mock, 1, 200_f64);
+    restarted_bot.poll_closed_candles().await.unwrap();
+
+    assert_eq!(restarted_bot.open_positions_count(), 0);
+    assert!(restarted_bot.account_balance() > balance);
+    assert!(mock.requests(ORDER_PATH).is_empty());
+}
+
+#[tokio::test]
+async fn paper_orders_fill_at_the_last_trade_price() {
+    let directory = tempfile::tempdir().unwrap();
+    let mock = MockBinance::start().await;
+    mock.respond(TICKER_PRICE_PATH, &ticker_price(95_f64));
+
+    let mut bot = paper_bot(&mock, directory.path()).await;
+
+    close_candle(&mock, 1, 90_f64);
+    bot.poll_closed_candles().await.unwrap();
+
+    // The quantity is sized at the candle close, the fill pays the ticker price with the
+    // default 0.05% slippage and 0.1% fee
+    let cost = (100_f64 / 90_f64) * 95_f64 * 1.0005 * 1.001;
+    assert_eq!(bot.open_positions_count(), 1);
+    assert!((bot.account_balance() - (1000_f64 - cost)).abs() < 1e-4);
+    assert_eq!(mock.requests(TICKER_PRICE_PATH).len(), 1);
+}
+
@@ -0,0 +1,217 @@
+use std::{
+    collections::{ HashMap, VecDeque },
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{ Arc, Mutex, MutexGuard },
+    time::Duration,
+};
+
+use binance_spot_connector_rust::http::Credentials;
+use hyper::{ service::{ make_service_fn, service_fn }, Body, Request, Response, Server, StatusCode };
+use tokio::sync::oneshot;
+
+use crate::api::binance::{ BinanceApi, RetryPolicy };
+
+pub const KLINES_PATH: &str = "/api/v3/klines";
+pub const SERVER_TIME_PATH: &str = "/api/v3/time";
+pub const EXCHANGE_INFO_PATH: &str = "/api/v3/exchangeInfo";
+pub const ORDER_PATH: &str = "/api/v3/order";
+pub const ACCOUNT_PATH: &str = "/api/v3/account";
+pub const TICKER_PRICE_PATH: &str = "/api/v3/ticker/price";
+
+pub const EXCHANGE_INFO_FIXTURE: &str = include_str!("fixtures/exchange_info.json");
+pub const ORDER_FIXTURE: &str = include_str!("fixtures/order.json");
+pub const ACCOUNT_FIXTURE: &str = include_str!("fixtures/account.json");
+
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: StatusCode,
+    pub body: String,
+    pub headers: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub query: String,
+}
+
+#[derive(Default)]
+struct MockState {
+    /// Responses served once, in order, before falling back to the default of the path
+    queued: HashMap<String, VecDeque<MockResponse>>,
+    defaults: HashMap<String, MockResponse>,
+    requests: Vec<RecordedRequest>,
+}
+
+/// In-process HTTP server answering Binance REST paths with scripted responses
+pub struct MockBinance {
+    address: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockBinance {
+    /// Starts the server on a free local port, answering the exchange info, balance and order
+    /// paths from fixtures
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let service_state = state.clone();
+
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+
+            async move {
+                Ok::<_, Infallible>(
+                    service_fn(move |request| {
+                        let response = respond(&state, &request);
+
+                        async move { Ok::<_, Infallible>(response) }
+                    })
+                )
+            }
+        });
+
+        let (shutdown, shutdown_signal) = oneshot::channel::<()>();
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let address = server.local_addr();
+
+        tokio::spawn(
+            server.with_graceful_shutdown(async {
+                shutdown_signal.await.ok();
+            })
+        );
+
+        let mock = Self { address, state, shutdown: Some(shutdown) };
+
+        mock.respond(EXCHANGE_INFO_PATH, EXCHANGE_INFO_FIXTURE);
+        mock.respond(ORDER_PATH, ORDER_FIXTURE);
+        mock.respond(ACCOUNT_PATH, ACCOUNT_FIXTURE);
+
+        mock
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    /// Client of the mock retrying failed requests without a noticeable backoff
+    pub fn client(&self) -> BinanceApi {
+        BinanceApi::with_base_url(
+            &self.url(),
+            Credentials::from_hmac("test-api-key", "test-api-secret")
+        ).with_retry_policy(RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(1),
+        })
+    }
+
+    /// Sets the response served for `path` whenever nothing is queued for it
+    pub fn respond(&self, path: &str, body: &str) {
+        self.lock().defaults.insert(path.to_string(), MockResponse {
+            status: StatusCode::OK,
+            body: body.to_string(),
+            headers: Vec::new(),
+        });
+    }
+
+    /// Queues a response served once for `path`
+    pub fn enqueue(&self, path: &str, status: StatusCode, body: &str) {
+        self.enqueue_response(path, MockResponse {
+            status,
+            body: body.to_string(),
+            headers: Vec::new(),
+        });
+    }
+
+    pub fn enqueue_response(&self, path: &str, response: MockResponse) {
+        self.lock().queued.entry(path.to_string()).or_default().push_back(response);
+    }
+
+    pub fn set_server_time(&self, server_time: u64) {
+        self.respond(SERVER_TIME_PATH, &format!(r#"{{"serverTime":{server_time}}}"#));
+    }
+
+    /// Paths of every request received, oldest first
+    pub fn paths(&self) -> Vec<String> {
+        self.lock().requests.iter().map(|request| request.path.clone()).collect()
+    }
+
+    /// Requests received for `path`, oldest first
+    pub fn requests(&self, path: &str) -> Vec<RecordedRequest> {
+        self.lock()
+            .requests.iter()
+            .filter(|request| request.path == path)
+            .cloned()
+            .collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Drop for MockBinance {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+fn respond(state: &Mutex<MockState>, request: &Request<Body>) -> Response<Body> {
+    let mut state = state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let path = request.uri().path().to_string();
+
+    state.requests.push(RecordedRequest {
+        method: request.method().to_string(),
+        path: path.clone(),
+        query: request.uri().query().unwrap_or_default().to_string(),
+    });
+
+    let response = state.queued
+        .get_mut(&path)
+        .and_then(VecDeque::pop_front)
+        .or_else(|| state.defaults.get(&path).cloned())
+        .unwrap_or(MockResponse {
+            status: StatusCode::NOT_FOUND,
+            body: format!(r#"{{"code":-1,"msg":"No mock response for {path}"}}"#),
+            headers: Vec::new(),
+        });
+
+    response.headers
+        .iter()
+        .fold(
+            Response::builder().status(response.status).header("Content-Type", "application/json"),
+            |builder, (name, value)| builder.header(name, value)
+        )
+        .body(Body::from(response.body))
+        .unwrap_or_default()
+}
+
+/// Klines response body of one candle per close, `interval` seconds apart starting at
+/// `open_time` seconds
+pub fn klines(open_time: u64, interval: u64, closes: &[f64]) -> String {
+    let klines: Vec<String> = closes
+        .iter()
+        .enumerate()
+        .map(|(index, close)| {
+            let open_time = (open_time + (index as u64) * interval) * 1000;
+            let close_time = open_time + interval * 1000 - 1;
+
+            format!(
+                r#"[{open_time},"{close}","{close}","{close}","{close}","10.0",{close_time},"{}",5,"5.0","{}","0"]"#,
+                close * 10_f64,
+                close * 5_f64
+            )
+        })
+        .collect();
+
+    format!("[{}]", klines.join(","))
+}
+
+/// Ticker price response body of the last BTCUSDT trade
+pub fn ticker_price(price: f64) -> String {
+    format!(r#"{{"symbol":"BTCUSDT","price":"{price}"}}"#)
+}
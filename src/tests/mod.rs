@@ -0,0 +1,3 @@
+mod integration;
+pub mod mock_binance;
+pub mod strategy;
@@ -0,0 +1,44 @@
+use config::{ Config, File, FileFormat };
+
+use crate::strategy::strategy::Strategy;
+
+/// Minimal BTCUSDT strategy on hourly candles the tests adjust with their own sections
+pub const BASE_STRATEGY: &str =
+    r#"
+symbol = "BTCUSDT"
+pair = "BTC/USDT"
+trading_symbol = "USDT"
+
+[timeframe]
+interval = "1h"
+tick = "1h"
+
+[timeframe.period_measurement]
+measure_bars = 20
+mean_calculation_method = "SimpleMA"
+
+[exchange]
+api = "binance"
+market_data = "polling"
+
+[risk_management]
+capital_per_trade = 0.1
+max_positions = 1
+max_drawdown = 50
+stop_loss = 5
+profit_level = 50
+
+[measurement_deviation]
+enter_deviation = 0.5
+"#;
+
+/// Base strategy with the `overrides` TOML merged on top of it
+pub fn strategy(overrides: &str) -> Strategy {
+    Config::builder()
+        .add_source(File::from_str(BASE_STRATEGY, FileFormat::Toml))
+        .add_source(File::from_str(overrides, FileFormat::Toml))
+        .build()
+        .unwrap()
+        .try_deserialize()
+        .unwrap()
+}